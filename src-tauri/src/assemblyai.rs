@@ -0,0 +1,146 @@
+use crate::audio::AIConfig;
+use crate::error::{AppError, ErrorKind};
+use crate::jobs::{self, CancelOnDrop, JobStatus, PollPolicy, Transcript, Utterance};
+use crate::retry::{send_with_connect_retry, send_with_retry};
+use serde_json::Value;
+
+/// Transcribes with AssemblyAI: upload the recording, submit a transcript job and poll it.
+///
+/// `config.whisper_model` is the speech model (`universal`, `slam-1`, ...). Speaker
+/// labels are requested, so recordings with several speakers come back labelled.
+pub async fn transcribe(client: &reqwest::Client, file_path: String, config: AIConfig) -> Result<String, AppError> {
+    let audio_data = std::fs::read(&file_path)
+        .map_err(|e| AppError::new(ErrorKind::InvalidAudio, format!("Failed to read audio file: {}", e)))?;
+
+    let upload_url = upload(client, &config, audio_data).await?;
+    let transcript_id = submit(client, &config, &upload_url).await?;
+
+    // 取消或失败时删除服务端的转写任务和上传的音频
+    let url = format!("{}/v2/transcript/{}", config.base_url, transcript_id);
+    let guard = {
+        let client = client.clone();
+        let url = url.clone();
+        let api_key = config.api_key.clone();
+        CancelOnDrop::new(async move {
+            let _ = client.delete(&url).header("Authorization", api_key).send().await;
+        })
+    };
+
+    let policy = PollPolicy::for_config(&config);
+    let transcript = jobs::poll_job(&transcript_id, &policy, || check(client, &config, &url)).await?;
+    guard.disarm();
+
+    Ok(transcript.into_text())
+}
+
+async fn upload(client: &reqwest::Client, config: &AIConfig, audio_data: Vec<u8>) -> Result<String, AppError> {
+    let url = format!("{}/v2/upload", config.base_url);
+    let response = send_with_connect_retry(&config.retry, || {
+        client
+            .post(&url)
+            .timeout(config.timeout())
+            .header("Authorization", &config.api_key)
+            .header("Content-Type", "application/octet-stream")
+            .body(audio_data.clone())
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    json["upload_url"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No upload_url in response"))
+}
+
+async fn submit(client: &reqwest::Client, config: &AIConfig, audio_url: &str) -> Result<String, AppError> {
+    let request_body = serde_json::json!({
+        "audio_url": audio_url,
+        "speech_model": config.whisper_model,
+        "language_detection": true,
+        "punctuate": true,
+        "format_text": true,
+        "speaker_labels": true
+    });
+
+    let url = format!("{}/v2/transcript", config.base_url);
+    // 超时后重发会创建重复计费的任务，只在连接失败时重试
+    let response = send_with_connect_retry(&config.retry, || {
+        client
+            .post(&url)
+            .timeout(config.timeout())
+            .header("Authorization", &config.api_key)
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    json["id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No transcript id in response"))
+}
+
+async fn check(client: &reqwest::Client, config: &AIConfig, url: &str) -> Result<JobStatus<Transcript>, AppError> {
+    let response = send_with_retry(&config.retry, || {
+        client
+            .get(url)
+            .timeout(config.timeout())
+            .header("Authorization", &config.api_key)
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    match json["status"].as_str().unwrap_or_default() {
+        "completed" => Ok(JobStatus::Finished(transcript(&json))),
+        "error" => {
+            let message = json["error"].as_str().unwrap_or("Transcription failed");
+            // 音频本身的问题（无语音、格式无法解码）和服务端故障分开提示
+            let kind = if message.contains("audio") || message.contains("speech") {
+                ErrorKind::InvalidAudio
+            } else {
+                ErrorKind::ServerError
+            };
+            Err(AppError {
+                provider_message: Some(message.to_string()),
+                ..AppError::new(kind, format!("AssemblyAI transcription failed: {}", message))
+            })
+        }
+        // queued / processing
+        _ => Ok(JobStatus::Running),
+    }
+}
+
+fn transcript(json: &Value) -> Transcript {
+    let utterances = json["utterances"]
+        .as_array()
+        .map(|utterances| {
+            utterances
+                .iter()
+                .filter_map(|u| {
+                    Some(Utterance {
+                        speaker: u["speaker"].as_str()?.to_string(),
+                        text: u["text"].as_str()?.to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Transcript {
+        text: json["text"].as_str().unwrap_or_default().to_string(),
+        utterances,
+    }
+}
@@ -1,23 +1,66 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use hound::WavWriter;
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use base64::Engine;
 use serde_json::Value;
+use crate::azure_auth::{self, AzureAuth};
+use crate::compat::{self, CompatProvider};
+use crate::error::{AppError, ErrorKind};
+use crate::ollama::{self, OllamaOptions};
+use crate::prompts;
+use crate::retry::{send_with_retry, RetryPolicy};
+use crate::stream::{self, DeltaSink};
+
+/// 内联发送的音频统一转成 16kHz 单声道 WAV
+const INLINE_AUDIO_SAMPLE_RATE: u32 = 16_000;
+
+const GEMINI_TRANSCRIBE_PROMPT: &str = "请将这段音频逐字转写为文本。中文使用简体字并加上标点，英文保持原文。只输出转写结果，不要添加任何解释。";
+
+/// 单次请求模式下随录音一起发送的用户指令，系统提示词与 format_text 相同
+pub const PROCESS_AUDIO_PROMPT: &str = "请先准确转写这段录音，再按要求清理转写结果。只输出清理后的文本，不要输出原始转写，也不要添加任何解释。";
+
 
 static mut RECORDING_STREAM: Option<cpal::Stream> = None;
 static mut WAV_WRITER: Option<Arc<Mutex<WavWriter<std::io::BufWriter<std::fs::File>>>>> = None;
 static mut CURRENT_FILE_PATH: Option<String> = None;
 
+/// A block of captured input exactly as cpal delivered it (interleaved f32).
+pub struct AudioFrame {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
 #[derive(Debug, Clone)]
 pub struct AIConfig {
     pub provider: String,
     pub api_key: String,
+    /// 需要两段凭证的服务商（腾讯云）的第二段密钥
+    pub api_secret: Option<String>,
     pub base_url: String,
     pub whisper_model: String,
     pub gpt_model: String,
     pub api_version: Option<String>,
     pub whisper_deployment: Option<String>,
     pub gpt_deployment: Option<String>,
+    /// Azure OpenAI 使用 Entra ID 令牌而不是 api-key 鉴权
+    pub azure_auth: Option<AzureAuth>,
+    pub retry: RetryPolicy,
+    pub timeout_ms: u64,
+    /// 本地模型推理线程数，None 时按 CPU 核数自动选择
+    #[cfg_attr(not(any(feature = "local-whisper", feature = "local-llm")), allow(dead_code))]
+    pub local_threads: Option<u32>,
+    /// 本地大模型的上下文长度（token 数）
+    #[cfg_attr(not(feature = "local-llm"), allow(dead_code))]
+    pub local_context_size: Option<u32>,
+    pub ollama: OllamaOptions,
+    /// 用户配置文件中定义的 OpenAI 兼容服务商，`provider` 是它的 id
+    pub custom: Option<CompatProvider>,
+    /// 文本格式化的系统提示词，由提示词模板渲染得到
+    pub system_prompt: String,
+    /// 文本格式化的温度，由输出模式决定
+    pub temperature: f32,
 }
 
 impl Default for AIConfig {
@@ -25,28 +68,61 @@ impl Default for AIConfig {
         Self {
             provider: "openai".to_string(),
             api_key: String::new(),
+            api_secret: None,
             base_url: "https://api.openai.com/v1".to_string(),
             whisper_model: "whisper-1".to_string(),
             gpt_model: "gpt-4o-mini".to_string(),
             api_version: None,
             whisper_deployment: None,
             gpt_deployment: None,
+            azure_auth: None,
+            retry: RetryPolicy::default(),
+            timeout_ms: 30_000,
+            local_threads: None,
+            local_context_size: None,
+            ollama: OllamaOptions::default(),
+            custom: None,
+            system_prompt: prompts::DEFAULT_PROMPT.to_string(),
+            temperature: 0.3,
         }
     }
 }
 
-pub async fn start_recording() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+impl AIConfig {
+    // 每次请求的总超时，避免服务商无响应时界面一直卡住
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    // 流式响应按数据块检查空闲超时（见 stream.rs），总时长只设一个宽松的上限
+    pub fn request_timeout(&self, streaming: bool) -> Duration {
+        if streaming {
+            self.timeout().max(Duration::from_secs(300))
+        } else {
+            self.timeout()
+        }
+    }
+}
+
+/// Starts capturing the default input device into a WAV file.
+///
+/// When `tap` is given every captured block is also sent to it, so streaming
+/// recognizers can consume audio while the user is still speaking.
+pub async fn start_recording(tap: Option<mpsc::Sender<AudioFrame>>) -> Result<(), AppError> {
     let host = cpal::default_host();
     let device = host.default_input_device()
-        .ok_or("No input device available")?;
+        .ok_or_else(|| AppError::new(ErrorKind::NoInputDevice, "No input device available"))?;
 
-    let config = device.default_input_config()?;
+    let config = device.default_input_config()
+        .map_err(recording_error("Failed to get input config"))?;
     
     // Create output file with timestamp in the current directory
     let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_err(recording_error("System clock error"))?
         .as_secs();
-    let file_path = std::env::current_dir()?
+    let file_path = std::env::current_dir()
+        .map_err(recording_error("Failed to get current directory"))?
         .join(format!("recording_{}.wav", timestamp))
         .to_string_lossy()
         .to_string();
@@ -58,9 +134,12 @@ pub async fn start_recording() -> Result<(), Box<dyn std::error::Error + Send +
         sample_format: hound::SampleFormat::Int,
     };
     
-    let writer = WavWriter::create(&file_path, spec)?;
+    let writer = WavWriter::create(&file_path, spec)
+        .map_err(recording_error("Failed to create WAV file"))?;
     let writer = Arc::new(Mutex::new(writer));
     let writer_clone = writer.clone();
+    let sample_rate = spec.sample_rate;
+    let channels = spec.channels;
     
     unsafe {
         WAV_WRITER = Some(writer);
@@ -76,12 +155,21 @@ pub async fn start_recording() -> Result<(), Box<dyn std::error::Error + Send +
                     let _ = writer.write_sample(sample);
                 }
             }
+            // 识别放在接收端线程里做，回调里只复制数据
+            if let Some(tap) = &tap {
+                let _ = tap.send(AudioFrame { samples: data.to_vec(), sample_rate, channels });
+            }
         },
         |err| eprintln!("An error occurred on the input audio stream: {}", err),
         None
-    )?;
+    ).map_err(|e| match e {
+        cpal::BuildStreamError::DeviceNotAvailable => {
+            AppError::new(ErrorKind::NoInputDevice, "Input device is no longer available")
+        }
+        e => recording_error("Failed to build input stream")(e),
+    })?;
 
-    stream.play()?;
+    stream.play().map_err(recording_error("Failed to start input stream"))?;
     
     unsafe {
         RECORDING_STREAM = Some(stream);
@@ -90,7 +178,7 @@ pub async fn start_recording() -> Result<(), Box<dyn std::error::Error + Send +
     Ok(())
 }
 
-pub async fn stop_recording() -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+pub async fn stop_recording() -> Result<String, AppError> {
     let file_path = unsafe {
         if let Some(stream) = RECORDING_STREAM.take() {
             drop(stream);
@@ -99,259 +187,233 @@ pub async fn stop_recording() -> Result<String, Box<dyn std::error::Error + Send
         if let Some(writer_arc) = WAV_WRITER.take() {
             // 获取 Arc 内部的 writer 并 finalize
             let writer = Arc::try_unwrap(writer_arc)
-                .map_err(|_| "Failed to unwrap writer")?
+                .map_err(|_| AppError::new(ErrorKind::Recording, "Failed to unwrap writer"))?
                 .into_inner()
-                .map_err(|_| "Failed to get writer from mutex")?;
-            writer.finalize()?;
+                .map_err(|_| AppError::new(ErrorKind::Recording, "Failed to get writer from mutex"))?;
+            writer.finalize().map_err(recording_error("Failed to finalize WAV file"))?;
         }
         
         // 返回保存的文件路径
-        CURRENT_FILE_PATH.take().ok_or("No file path recorded")?
+        CURRENT_FILE_PATH.take()
+            .ok_or_else(|| AppError::new(ErrorKind::Recording, "No file path recorded"))?
     };
     
     Ok(file_path)
 }
 
-pub async fn transcribe_audio(file_path: String, config: AIConfig) -> Result<String, String> {
-    match config.provider.as_str() {
-        "openai" => transcribe_openai(file_path, config).await,
-        "deepseek" => transcribe_deepseek(file_path, config).await,
-        "zhipu" => transcribe_zhipu(file_path, config).await,
-        "moonshot" => transcribe_moonshot(file_path, config).await,
-        "azure" => transcribe_azure(file_path, config).await,
-        "ollama" => transcribe_ollama(file_path, config).await,
-        _ => Err(format!("Unsupported AI provider: {}", config.provider)),
-    }
+fn recording_error<E: std::fmt::Display>(context: &'static str) -> impl Fn(E) -> AppError {
+    move |e| AppError::new(ErrorKind::Recording, format!("{}: {}", context, e))
 }
 
-async fn transcribe_openai(file_path: String, config: AIConfig) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    
-    // Read the audio file
-    let audio_data = std::fs::read(&file_path)
-        .map_err(|e| format!("Failed to read audio file: {}", e))?;
-    
-    // Create multipart form
-    let form = reqwest::multipart::Form::new()
-        .text("model", config.whisper_model)
-        .text("language", "zh")
-        .text("response_format", "json")
-        .part("file", reqwest::multipart::Part::bytes(audio_data)
-            .file_name("audio.wav")
-            .mime_str("audio/wav").unwrap());
-    
-    let url = format!("{}/audio/transcriptions", config.base_url);
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", config.api_key))
-        .multipart(form)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
-    
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("API request failed: {}", error_text));
+/// Reads a recording as mono f32 samples at `target_rate`, as speech engines expect.
+pub fn read_wav_mono(file_path: &str, target_rate: u32) -> Result<Vec<f32>, AppError> {
+    let invalid = |e: hound::Error| AppError::new(ErrorKind::InvalidAudio, format!("Failed to read audio file: {}", e));
+
+    let mut reader = hound::WavReader::open(file_path).map_err(invalid)?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>().map_err(invalid)?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(invalid)?
+        }
+    };
+
+    let mono = downmix(&samples, spec.channels);
+    Ok(resample(&mono, spec.sample_rate, target_rate))
+}
+
+/// Encodes mono f32 samples as an in-memory 16-bit PCM WAV file.
+pub fn encode_wav_mono(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>, AppError> {
+    let invalid = |e: hound::Error| AppError::new(ErrorKind::InvalidAudio, format!("Failed to encode audio: {}", e));
+
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut buffer, spec).map_err(invalid)?;
+    for &sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).map_err(invalid)?;
     }
-    
-    let json: Value = response.json().await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
-    
-    let text = json["text"].as_str()
-        .ok_or("No text in response")?
-        .to_string();
-    
-    // Clean up the audio file
-    let _ = std::fs::remove_file(&file_path);
-    
-    Ok(text)
+    writer.finalize().map_err(invalid)?;
+    Ok(buffer.into_inner())
 }
 
-async fn transcribe_deepseek(file_path: String, config: AIConfig) -> Result<String, String> {
-    // DeepSeek 使用与 OpenAI 兼容的接口
-    transcribe_openai(file_path, config).await
+/// Reads a recording and returns it as base64 16kHz mono WAV for inline upload.
+///
+/// Inline audio counts against the request size limit (20MB for Gemini), and the
+/// downsampled file is many times smaller than the device-rate recording.
+pub fn inline_wav_base64(file_path: &str) -> Result<String, AppError> {
+    let samples = read_wav_mono(file_path, INLINE_AUDIO_SAMPLE_RATE)?;
+    let wav = encode_wav_mono(&samples, INLINE_AUDIO_SAMPLE_RATE)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(wav))
 }
 
-async fn transcribe_zhipu(file_path: String, config: AIConfig) -> Result<String, String> {
-    // 智谱AI GLM 使用与 OpenAI 兼容的接口
-    transcribe_openai(file_path, config).await
+/// Averages interleaved channels into a single channel.
+pub fn downmix(interleaved: &[f32], channels: u16) -> Vec<f32> {
+    if channels <= 1 {
+        return interleaved.to_vec();
+    }
+    interleaved
+        .chunks(channels as usize)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
 }
 
-async fn transcribe_moonshot(file_path: String, config: AIConfig) -> Result<String, String> {
-    // Moonshot 使用与 OpenAI 兼容的接口
-    transcribe_openai(file_path, config).await
+/// Linear-interpolation resampler; good enough for speech recognition input.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio) as usize;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let index = pos as usize;
+            let frac = (pos - index as f64) as f32;
+            let a = samples[index];
+            let b = *samples.get(index + 1).unwrap_or(&a);
+            a + (b - a) * frac
+        })
+        .collect()
 }
 
-async fn transcribe_azure(file_path: String, config: AIConfig) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    
+pub async fn transcribe_audio(client: &reqwest::Client, file_path: String, config: AIConfig) -> Result<String, AppError> {
+    // config.json 中的同名服务商覆盖内置的
+    if let Some(provider) = config.custom.clone() {
+        return compat::transcribe(client, file_path, config, &provider).await;
+    }
+    match config.provider.as_str() {
+        // DeepSeek、智谱AI、Moonshot 使用与 OpenAI 兼容的接口
+        "openai" | "deepseek" | "zhipu" | "moonshot" => compat::transcribe(client, file_path, config, &CompatProvider::openai()).await,
+        "azure" => transcribe_azure(client, file_path, config).await,
+        // Ollama 没有语音识别接口
+        "ollama" => Err(AppError::new(ErrorKind::ProviderUnsupported, "ollama does not support transcription")),
+        "gemini" => transcribe_gemini(client, file_path, config).await,
+        "dashscope" => crate::dashscope::transcribe(client, file_path, config).await,
+        "tencent" => crate::tencent::transcribe(client, file_path, config).await,
+        "assemblyai" => crate::assemblyai::transcribe(client, file_path, config).await,
+        #[cfg(feature = "local-whisper")]
+        "whisper-local" => crate::local_whisper::transcribe(file_path, config).await,
+        "openai-realtime" => crate::realtime::transcribe(file_path, config).await,
+        #[cfg(feature = "local-vosk")]
+        "vosk" => crate::streaming_asr::transcribe(file_path, config).await,
+        _ => Err(AppError::unsupported_provider(&config.provider)),
+    }
+}
+
+async fn transcribe_azure(client: &reqwest::Client, file_path: String, config: AIConfig) -> Result<String, AppError> {
     // Read the audio file
     let audio_data = std::fs::read(&file_path)
-        .map_err(|e| format!("Failed to read audio file: {}", e))?;
-    
-    // Create multipart form
-    let form = reqwest::multipart::Form::new()
-        .text("language", "zh")
-        .text("response_format", "json")
-        .part("file", reqwest::multipart::Part::bytes(audio_data)
-            .file_name("audio.wav")
-            .mime_str("audio/wav").unwrap());
+        .map_err(|e| AppError::new(ErrorKind::InvalidAudio, format!("Failed to read audio file: {}", e)))?;
     
     let deployment = config.whisper_deployment.as_deref().unwrap_or("whisper");
     let api_version = config.api_version.as_deref().unwrap_or("2024-02-01");
     let url = format!("{}/openai/deployments/{}/audio/transcriptions?api-version={}", 
                      config.base_url, deployment, api_version);
-    
-    let response = client
-        .post(&url)
-        .header("api-key", config.api_key)
-        .multipart(form)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
+    let (auth_name, auth_value) = azure_auth::auth_header(client, &config).await?;
+    
+    let response = send_with_retry(&config.retry, || {
+        // Create multipart form
+        let form = reqwest::multipart::Form::new()
+            .text("language", "zh")
+            .text("response_format", "json")
+            .part("file", reqwest::multipart::Part::bytes(audio_data.clone())
+                .file_name("audio.wav")
+                .mime_str("audio/wav").unwrap());
+
+        client
+            .post(&url)
+            .timeout(config.timeout())
+            .header(auth_name, &auth_value)
+            .multipart(form)
+    })
+    .await?;
     
     if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("API request failed: {}", error_text));
+        return Err(AppError::from_response(response).await);
     }
     
-    let json: Value = response.json().await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let json: Value = response.json().await?;
     
     let text = json["text"].as_str()
-        .ok_or("No text in response")?
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No text in response"))?
         .to_string();
     
-    // Clean up the audio file
-    let _ = std::fs::remove_file(&file_path);
-    
     Ok(text)
 }
 
-async fn transcribe_ollama(file_path: String, config: AIConfig) -> Result<String, String> {
-    // Ollama 本地部署的 Whisper 模型
-    let client = reqwest::Client::new();
+/// Transcribes with Gemini by sending the audio inline in a generateContent request.
+async fn transcribe_gemini(client: &reqwest::Client, file_path: String, config: AIConfig) -> Result<String, AppError> {
+    let audio_data = inline_wav_base64(&file_path)?;
     
-    // Read the audio file
-    let audio_data = std::fs::read(&file_path)
-        .map_err(|e| format!("Failed to read audio file: {}", e))?;
-    
-    // Create multipart form
-    let form = reqwest::multipart::Form::new()
-        .text("model", config.whisper_model)
-        .text("language", "zh")
-        .text("response_format", "json")
-        .part("file", reqwest::multipart::Part::bytes(audio_data)
-            .file_name("audio.wav")
-            .mime_str("audio/wav").unwrap());
-    
-    let url = format!("{}/v1/audio/transcriptions", config.base_url);
-    let response = client
-        .post(&url)
-        .multipart(form)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
-    
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("API request failed: {}", error_text));
-    }
-    
-    let json: Value = response.json().await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
-    
-    let text = json["text"].as_str()
-        .ok_or("No text in response")?
-        .to_string();
+    let request_body = serde_json::json!({
+        "contents": [
+            {
+                "role": "user",
+                "parts": [
+                    { "inline_data": { "mime_type": "audio/wav", "data": audio_data } },
+                    { "text": GEMINI_TRANSCRIBE_PROMPT }
+                ]
+            }
+        ],
+        "generationConfig": { "temperature": 0 }
+    });
     
-    // Clean up the audio file
-    let _ = std::fs::remove_file(&file_path);
+    let text = gemini_generate(client, &config, &config.whisper_model, &request_body, None).await?;
     
-    Ok(text)
+    Ok(text.trim().to_string())
 }
 
-pub async fn format_text(text: String, config: AIConfig) -> Result<String, String> {
+/// Formats the transcript with the text provider.
+///
+/// When `on_delta` is given the request is streamed and every text fragment is passed
+/// to it as it arrives; the complete text is still returned at the end.
+pub async fn format_text(client: &reqwest::Client, text: String, config: AIConfig, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    if let Some(provider) = config.custom.clone() {
+        return compat::format_text(client, text, config, &provider, on_delta).await;
+    }
     match config.provider.as_str() {
-        "openai" => format_text_openai(text, config).await,
-        "deepseek" => format_text_deepseek(text, config).await,
-        "zhipu" => format_text_zhipu(text, config).await,
-        "moonshot" => format_text_moonshot(text, config).await,
-        "azure" => format_text_azure(text, config).await,
-        "anthropic" => format_text_anthropic(text, config).await,
-        "ollama" => format_text_ollama(text, config).await,
-        _ => Err(format!("Unsupported AI provider: {}", config.provider)),
+        "openai" | "deepseek" | "zhipu" | "moonshot" => compat::format_text(client, text, config, &CompatProvider::openai(), on_delta).await,
+        "azure" => format_text_azure(client, text, config, on_delta).await,
+        "anthropic" => format_text_anthropic(client, text, config, on_delta).await,
+        "ollama" => format_text_ollama(client, text, config, on_delta).await,
+        "gemini" => format_text_gemini(client, text, config, on_delta).await,
+        #[cfg(feature = "local-llm")]
+        "llama-local" => crate::local_llm::format_text(text, config, on_delta).await,
+        _ => Err(AppError::unsupported_provider(&config.provider)),
     }
 }
 
-async fn format_text_openai(text: String, config: AIConfig) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    
-    let messages = serde_json::json!([
-        {
-            "role": "system",
-            "content": "你是专业的中文文本清理工具。请将语音转写的文本进行清理：1）如果输入是英文但内容是中文意思，请直接翻译成对应的中文；2）删除语气词（嗯、啊、那个等）；3）去除重复词语；4）修正语法错误；5）保持原意不变，不要添加任何新内容；6）输出简洁的中文文本，不要使用Markdown格式。特别注意：如果输入的英文明显是中文语音的错误识别结果，请直接转换为正确的中文表达。"
-        },
-        {
-            "role": "user",
-            "content": text
+/// Transcribes and formats in one request to an audio-capable chat model.
+///
+/// Returns the same cleaned text as `transcribe_audio` followed by `format_text`,
+/// using the text provider settings, in roughly half the time.
+pub async fn process_audio(client: &reqwest::Client, file_path: String, config: AIConfig, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let text = match (config.custom.clone(), config.provider.as_str()) {
+        (Some(provider), _) => compat::process_audio(client, &file_path, config, &provider, on_delta).await,
+        (None, "openai" | "deepseek" | "zhipu" | "moonshot") => {
+            compat::process_audio(client, &file_path, config, &CompatProvider::openai(), on_delta).await
         }
-    ]);
-    
-    let request_body = serde_json::json!({
-        "model": config.gpt_model,
-        "messages": messages,
-        "temperature": 0.3
-    });
+        (None, "gemini") => process_audio_gemini(client, &file_path, config, on_delta).await,
+        _ => Err(AppError::unsupported_provider(&config.provider)),
+    }?;
     
-    let url = format!("{}/chat/completions", config.base_url);
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", config.api_key))
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
-    
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("API request failed: {}", error_text));
-    }
-    
-    let json: Value = response.json().await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
-    
-    let formatted_text = json["choices"][0]["message"]["content"]
-        .as_str()
-        .ok_or("No content in response")?
-        .to_string();
-    
-    Ok(formatted_text)
-}
-
-async fn format_text_deepseek(text: String, config: AIConfig) -> Result<String, String> {
-    // DeepSeek 使用与 OpenAI 兼容的接口
-    format_text_openai(text, config).await
-}
-
-async fn format_text_zhipu(text: String, config: AIConfig) -> Result<String, String> {
-    // 智谱AI GLM 使用与 OpenAI 兼容的接口
-    format_text_openai(text, config).await
-}
-
-async fn format_text_moonshot(text: String, config: AIConfig) -> Result<String, String> {
-    // Moonshot 使用与 OpenAI 兼容的接口
-    format_text_openai(text, config).await
+    Ok(text)
 }
 
-async fn format_text_azure(text: String, config: AIConfig) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    
+async fn format_text_azure(client: &reqwest::Client, text: String, config: AIConfig, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
     let messages = serde_json::json!([
         {
             "role": "system",
-            "content": "你是专业的中文文本清理工具。请将语音转写的文本进行清理：1）如果输入是英文但内容是中文意思，请直接翻译成对应的中文；2）删除语气词（嗯、啊、那个等）；3）去除重复词语；4）修正语法错误；5）保持原意不变，不要添加任何新内容；6）输出简洁的中文文本，不要使用Markdown格式。特别注意：如果输入的英文明显是中文语音的错误识别结果，请直接转换为正确的中文表达。"
+            "content": config.system_prompt
         },
         {
             "role": "user",
@@ -359,48 +421,55 @@ async fn format_text_azure(text: String, config: AIConfig) -> Result<String, Str
         }
     ]);
     
+    let streaming = on_delta.is_some();
     let request_body = serde_json::json!({
         "messages": messages,
-        "temperature": 0.3
+        "temperature": config.temperature,
+        "stream": streaming
     });
     
     let deployment = config.gpt_deployment.as_deref().unwrap_or("gpt-4");
     let api_version = config.api_version.as_deref().unwrap_or("2024-02-01");
     let url = format!("{}/openai/deployments/{}/chat/completions?api-version={}", 
                      config.base_url, deployment, api_version);
-    
-    let response = client
-        .post(&url)
-        .header("api-key", config.api_key)
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
+    let (auth_name, auth_value) = azure_auth::auth_header(client, &config).await?;
+    
+    let response = send_with_retry(&config.retry, || {
+        client
+            .post(&url)
+            .timeout(config.request_timeout(streaming))
+            .header(auth_name, &auth_value)
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+    })
+    .await?;
     
     if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("API request failed: {}", error_text));
+        return Err(AppError::from_response(response).await);
+    }
+    
+    if let Some(on_delta) = on_delta {
+        return stream::collect_openai_sse(response, config.timeout(), on_delta).await;
     }
     
-    let json: Value = response.json().await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let json: Value = response.json().await?;
     
     let formatted_text = json["choices"][0]["message"]["content"]
         .as_str()
-        .ok_or("No content in response")?
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No content in response"))?
         .to_string();
     
     Ok(formatted_text)
 }
 
-async fn format_text_anthropic(text: String, config: AIConfig) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    
+async fn format_text_anthropic(client: &reqwest::Client, text: String, config: AIConfig, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let streaming = on_delta.is_some();
     let request_body = serde_json::json!({
         "model": config.gpt_model,
         "max_tokens": 1024,
-        "system": "你是专业的中文文本清理工具。请将语音转写的文本进行清理：1）如果输入是英文但内容是中文意思，请直接翻译成对应的中文；2）删除语气词（嗯、啊、那个等）；3）去除重复词语；4）修正语法错误；5）保持原意不变，不要添加任何新内容；6）输出简洁的中文文本，不要使用Markdown格式。特别注意：如果输入的英文明显是中文语音的错误识别结果，请直接转换为正确的中文表达。",
+        "temperature": config.temperature,
+        "stream": streaming,
+        "system": config.system_prompt,
         "messages": [
             {
                 "role": "user",
@@ -410,78 +479,138 @@ async fn format_text_anthropic(text: String, config: AIConfig) -> Result<String,
     });
     
     let url = format!("{}/v1/messages", config.base_url);
-    let response = client
-        .post(&url)
-        .header("x-api-key", config.api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
+    let response = send_with_retry(&config.retry, || {
+        client
+            .post(&url)
+            .timeout(config.request_timeout(streaming))
+            .header("x-api-key", &config.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+    })
+    .await?;
     
     if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("API request failed: {}", error_text));
+        return Err(AppError::from_response(response).await);
     }
     
-    let json: Value = response.json().await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    if let Some(on_delta) = on_delta {
+        return stream::collect_anthropic_sse(response, config.timeout(), on_delta).await;
+    }
+    
+    let json: Value = response.json().await?;
     
     let formatted_text = json["content"][0]["text"]
         .as_str()
-        .ok_or("No content in response")?
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No content in response"))?
         .to_string();
     
     Ok(formatted_text)
 }
 
-async fn format_text_ollama(text: String, config: AIConfig) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    
-    let messages = serde_json::json!([
-        {
-            "role": "system",
-            "content": "你是专业的中文文本清理工具。请将语音转写的文本进行清理：1）如果输入是英文但内容是中文意思，请直接翻译成对应的中文；2）删除语气词（嗯、啊、那个等）；3）去除重复词语；4）修正语法错误；5）保持原意不变，不要添加任何新内容；6）输出简洁的中文文本，不要使用Markdown格式。特别注意：如果输入的英文明显是中文语音的错误识别结果，请直接转换为正确的中文表达。"
+async fn format_text_gemini(client: &reqwest::Client, text: String, config: AIConfig, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let request_body = serde_json::json!({
+        "systemInstruction": {
+            "parts": [{ "text": config.system_prompt }]
         },
-        {
-            "role": "user",
-            "content": text
-        }
-    ]);
+        "contents": [
+            {
+                "role": "user",
+                "parts": [{ "text": text }]
+            }
+        ],
+        "generationConfig": { "temperature": config.temperature }
+    });
+    
+    gemini_generate(client, &config, &config.gpt_model, &request_body, on_delta).await
+}
+
+async fn process_audio_gemini(client: &reqwest::Client, file_path: &str, config: AIConfig, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let audio_data = inline_wav_base64(file_path)?;
     
     let request_body = serde_json::json!({
-        "model": config.gpt_model,
-        "messages": messages,
-        "stream": false
+        "systemInstruction": {
+            "parts": [{ "text": config.system_prompt }]
+        },
+        "contents": [
+            {
+                "role": "user",
+                "parts": [
+                    { "inline_data": { "mime_type": "audio/wav", "data": audio_data } },
+                    { "text": PROCESS_AUDIO_PROMPT }
+                ]
+            }
+        ],
+        "generationConfig": { "temperature": config.temperature }
     });
     
-    let url = format!("{}/v1/chat/completions", config.base_url);
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send request: {}", e))?;
+    gemini_generate(client, &config, &config.gpt_model, &request_body, on_delta).await
+}
+
+async fn gemini_generate(client: &reqwest::Client, config: &AIConfig, model: &str, request_body: &Value, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    // 流式输出使用 streamGenerateContent 的 SSE 模式
+    let streaming = on_delta.is_some();
+    let url = if streaming {
+        format!("{}/models/{}:streamGenerateContent?alt=sse", config.base_url, model)
+    } else {
+        format!("{}/models/{}:generateContent", config.base_url, model)
+    };
+    let response = send_with_retry(&config.retry, || {
+        client
+            .post(&url)
+            .timeout(config.request_timeout(streaming))
+            .header("x-goog-api-key", &config.api_key)
+            .header("Content-Type", "application/json")
+            .json(request_body)
+    })
+    .await?;
     
     if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("API request failed: {}", error_text));
+        return Err(AppError::from_response(response).await);
     }
     
-    let json: Value = response.json().await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    if let Some(on_delta) = on_delta {
+        return stream::collect_gemini_sse(response, config.timeout(), on_delta).await;
+    }
     
-    let formatted_text = json["choices"][0]["message"]["content"]
-        .as_str()
-        .ok_or("No content in response")?
-        .to_string();
+    let json: Value = response.json().await?;
     
-    Ok(formatted_text)
+    gemini_text(&json)
+}
+
+// Joins the text parts of the first candidate; a blocked prompt has no candidates at all.
+fn gemini_text(json: &Value) -> Result<String, AppError> {
+    if let Some(reason) = json["promptFeedback"]["blockReason"].as_str() {
+        return Err(AppError::new(ErrorKind::InvalidRequest, format!("Request blocked by Gemini: {}", reason)));
+    }
+    
+    let parts = json["candidates"][0]["content"]["parts"]
+        .as_array()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No content in response"))?;
+    
+    Ok(parts.iter().filter_map(|part| part["text"].as_str()).collect())
 }
 
-pub async fn copy_to_clipboard(text: String) -> Result<(), String> {
+async fn format_text_ollama(client: &reqwest::Client, text: String, config: AIConfig, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    // 流式输出需要原生 /api/chat（NDJSON），OpenAI 兼容接口不支持 keep_alive 等选项
+    if config.ollama.native || on_delta.is_some() {
+        let messages = serde_json::json!([
+            {
+                "role": "system",
+                "content": config.system_prompt
+            },
+            {
+                "role": "user",
+                "content": text
+            }
+        ]);
+        return ollama::chat(client, &config, messages, on_delta).await;
+    }
+    
+    compat::format_text(client, text, config, &CompatProvider::ollama(), None).await
+}
+
+pub async fn copy_to_clipboard(text: String) -> Result<(), AppError> {
     use std::process::Command;
     
     // Use Windows clipboard command
@@ -491,9 +620,9 @@ pub async fn copy_to_clipboard(text: String) -> Result<(), String> {
             .arg("-Command")
             .arg(format!("Set-Clipboard -Value '{}'", text.replace("'", "''")))
             .spawn()
-            .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+            .map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Failed to copy to clipboard: {}", e)))?;
         
-        cmd.wait().map_err(|e| format!("Clipboard command failed: {}", e))?;
+        cmd.wait().map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Clipboard command failed: {}", e)))?;
     }
     
     // Use macOS clipboard command
@@ -502,15 +631,15 @@ pub async fn copy_to_clipboard(text: String) -> Result<(), String> {
         let mut cmd = Command::new("pbcopy")
             .stdin(std::process::Stdio::piped())
             .spawn()
-            .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+            .map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Failed to copy to clipboard: {}", e)))?;
         
         if let Some(stdin) = cmd.stdin.as_mut() {
             use std::io::Write;
             stdin.write_all(text.as_bytes())
-                .map_err(|e| format!("Failed to write to clipboard: {}", e))?;
+                .map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Failed to write to clipboard: {}", e)))?;
         }
         
-        cmd.wait().map_err(|e| format!("Clipboard command failed: {}", e))?;
+        cmd.wait().map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Clipboard command failed: {}", e)))?;
     }
     
     // Use Linux clipboard command
@@ -521,16 +650,42 @@ pub async fn copy_to_clipboard(text: String) -> Result<(), String> {
             .arg("clipboard")
             .stdin(std::process::Stdio::piped())
             .spawn()
-            .map_err(|e| format!("Failed to copy to clipboard: {}", e))?;
+            .map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Failed to copy to clipboard: {}", e)))?;
         
         if let Some(stdin) = cmd.stdin.as_mut() {
             use std::io::Write;
             stdin.write_all(text.as_bytes())
-                .map_err(|e| format!("Failed to write to clipboard: {}", e))?;
+                .map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Failed to write to clipboard: {}", e)))?;
         }
         
-        cmd.wait().map_err(|e| format!("Clipboard command failed: {}", e))?;
+        cmd.wait().map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Clipboard command failed: {}", e)))?;
     }
     
     Ok(())
 }
+
+/// Reads the current clipboard text with the same platform commands `copy_to_clipboard` uses.
+pub async fn read_clipboard() -> Result<String, AppError> {
+    use std::process::Command;
+    
+    #[cfg(target_os = "windows")]
+    let output = Command::new("powershell")
+        .arg("-Command")
+        .arg("Get-Clipboard -Raw")
+        .output();
+    
+    #[cfg(target_os = "macos")]
+    let output = Command::new("pbpaste").output();
+    
+    #[cfg(target_os = "linux")]
+    let output = Command::new("xclip")
+        .arg("-selection")
+        .arg("clipboard")
+        .arg("-o")
+        .output();
+    
+    let output = output
+        .map_err(|e| AppError::new(ErrorKind::Clipboard, format!("Failed to read clipboard: {}", e)))?;
+    
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
@@ -0,0 +1,114 @@
+use crate::audio::AIConfig;
+use crate::error::{AppError, ErrorKind};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_SCOPE: &str = "https://cognitiveservices.azure.com/.default";
+
+// 提前刷新，避免令牌在请求途中过期
+const REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// Microsoft Entra ID client-credentials settings for tenants with key auth disabled.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AzureAuth {
+    pub tenant_id: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// 令牌地址，默认 https://login.microsoftonline.com/{tenantId}/oauth2/v2.0/token；
+    /// 国内的世纪互联云需要改为 login.partner.microsoftonline.cn
+    pub token_endpoint: Option<String>,
+    /// 默认 https://cognitiveservices.azure.com/.default
+    pub scope: Option<String>,
+}
+
+impl AzureAuth {
+    fn token_endpoint(&self) -> String {
+        match &self.token_endpoint {
+            Some(endpoint) if !endpoint.is_empty() => endpoint.clone(),
+            _ => format!("https://login.microsoftonline.com/{}/oauth2/v2.0/token", self.tenant_id),
+        }
+    }
+
+    fn scope(&self) -> &str {
+        self.scope.as_deref().filter(|s| !s.is_empty()).unwrap_or(DEFAULT_SCOPE)
+    }
+}
+
+struct CachedToken {
+    // 令牌地址、client_id 和 scope，任一变化都需要重新获取
+    key: String,
+    access_token: String,
+    expires_at: Instant,
+}
+
+static TOKEN: Mutex<Option<CachedToken>> = Mutex::new(None);
+
+/// Returns the auth header for an Azure OpenAI request: a bearer token when
+/// `config.azure_auth` is set, otherwise the `api-key` header.
+pub async fn auth_header(client: &reqwest::Client, config: &AIConfig) -> Result<(&'static str, String), AppError> {
+    match &config.azure_auth {
+        Some(auth) => Ok(("Authorization", format!("Bearer {}", access_token(client, auth).await?))),
+        None => Ok(("api-key", config.api_key.clone())),
+    }
+}
+
+async fn access_token(client: &reqwest::Client, auth: &AzureAuth) -> Result<String, AppError> {
+    let endpoint = auth.token_endpoint();
+    let key = format!("{}|{}|{}", endpoint, auth.client_id, auth.scope());
+
+    if let Some(cached) = TOKEN.lock().unwrap().as_ref() {
+        if cached.key == key && Instant::now() + REFRESH_MARGIN < cached.expires_at {
+            return Ok(cached.access_token.clone());
+        }
+    }
+
+    let response = client
+        .post(&endpoint)
+        .timeout(Duration::from_secs(30))
+        .form(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", auth.client_id.as_str()),
+            ("client_secret", auth.client_secret.as_str()),
+            ("scope", auth.scope()),
+        ])
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        // {"error": "invalid_client", "error_description": "AADSTS7000215: Invalid client secret provided..."}
+        // 代理或网关返回的错误页不是 JSON，直接使用原文
+        let body = response.text().await.unwrap_or_default();
+        let json: Value = serde_json::from_str(&body).unwrap_or_default();
+        let message = json["error_description"]
+            .as_str()
+            .or_else(|| json["error"].as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| if body.trim().is_empty() { "Unknown error".to_string() } else { body.trim().to_string() });
+        return Err(AppError {
+            status: Some(status.as_u16()),
+            provider_message: Some(message.clone()),
+            ..AppError::new(ErrorKind::Auth, format!("Failed to get Entra ID token ({}): {}", status.as_u16(), message))
+        });
+    }
+
+    let json: Value = response.json().await?;
+    let access_token = json["access_token"]
+        .as_str()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No access_token in token response"))?
+        .to_string();
+    let expires_in = json["expires_in"]
+        .as_u64()
+        .or_else(|| json["expires_in"].as_str().and_then(|s| s.parse().ok()))
+        .unwrap_or(3600);
+
+    *TOKEN.lock().unwrap() = Some(CachedToken {
+        key,
+        access_token: access_token.clone(),
+        expires_at: Instant::now() + Duration::from_secs(expires_in),
+    });
+    Ok(access_token)
+}
@@ -0,0 +1,248 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+// 几乎只作语气词使用，出现在任何位置都删掉
+const CHINESE_FILLERS: &str = r"(?:嗯+|呃+|唔+)[，,、 ]*";
+
+// 也有实际含义，只在单独成句时删掉，如 "那个，我想..." 中的 "那个"
+const CHINESE_WEAK_FILLERS: &str = r"(^|[，。！？、；,.!?;\s])(?:那个|这个|就是说|就是|然后呢|啊|额|哎)+[，,、 ]+";
+
+// "er"、"mm" 也是单词或单位（"5 mm screw"），不算语气词
+const ENGLISH_FILLERS: &[&str] = &["um", "umm", "uh", "uhh", "uhm", "erm", "hmm"];
+
+// 英文只在前后都是逗号时才算语气词，如 "it's, like, broken"
+const ENGLISH_WEAK_FILLERS: &str = r"(?i)\s*[,，]\s*(?:like|you know|i mean)\s*[,，]\s*";
+
+/// Rule-based cleanup for Chinese and English transcripts, used instead of the
+/// model in clean mode when uploads are disabled or the text provider fails.
+///
+/// Removes fillers, collapses stuttered repeats and fixes spacing; it never
+/// rewrites sentences, so it is much weaker than the model.
+pub fn clean(text: &str) -> String {
+    let text = fix_spacing(text);
+    let text = collapse_cjk_repeats(&text);
+    let text = collapse_word_repeats(&text);
+    let text = remove_fillers(&text);
+    tidy_punctuation(&text)
+}
+
+/// Whether `NO_UPLOAD` forbids sending text to remote services.
+pub fn uploads_disabled() -> bool {
+    std::env::var("NO_UPLOAD").is_ok_and(|value| matches!(value.trim(), "1" | "true" | "yes"))
+}
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}') || is_cjk_punctuation(c)
+}
+
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c, '\u{3000}'..='\u{303f}' | '\u{ff00}'..='\u{ffef}')
+}
+
+fn is_han(c: char) -> bool {
+    is_cjk(c) && !is_cjk_punctuation(c)
+}
+
+// 合并连续空白；汉字之间（识别结果常见 "我 想 要"）和标点前面的空格去掉，中英文之间的空格保持原样
+fn fix_spacing(text: &str) -> String {
+    let chars: Vec<char> = text.trim().chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == ' ' || c == '\t' || c == '\u{3000}' {
+            let start = i;
+            while i < chars.len() && matches!(chars[i], ' ' | '\t' | '\u{3000}') {
+                i += 1;
+            }
+            let prev = out.chars().next_back();
+            let next = chars.get(i).copied();
+            let drop = match (prev, next) {
+                (None, _) | (_, None) | (Some('\n'), _) | (_, Some('\n')) => true,
+                (Some(prev), Some(next)) => {
+                    (is_cjk(prev) && is_cjk(next))
+                        || is_cjk_punctuation(prev)
+                        || is_cjk_punctuation(next)
+                        || ",.!?;:)".contains(next)
+                }
+            };
+            if !drop && start < i {
+                out.push(' ');
+            }
+            continue;
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+// 常以叠词出现的单字，口吃重复时保留两个："谢谢谢谢" -> "谢谢"
+const DOUBLED: &str = "谢看试想说走等听问找聊";
+
+// 本来就会连用多次的字，如笑声 "哈哈哈" 和数字 "六六六"，重复几次都保持原样
+const REPEATABLE: &str = "哈呵嘿嘻零一二三四五六七八九十";
+
+// "我我我想" -> "我想"，"我们，我们" -> "我们"，"这个，这个" -> "这个"。
+// 单字重叠（"看看"、"谢谢"）是正常用法，单字要重复三次以上才合并；
+// 双字以上只合并中间有停顿的重复，"研究研究"、"一个一个" 这类重叠保持原样
+fn collapse_cjk_repeats(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out: Vec<char> = Vec::with_capacity(chars.len());
+    let mut i = 0;
+
+    'outer: while i < chars.len() {
+        for n in (1..=4).rev() {
+            let unit = match chars.get(i..i + n) {
+                Some(unit) if unit.iter().all(|&c| is_han(c)) => unit,
+                _ => continue,
+            };
+            if n == 1 && REPEATABLE.contains(unit[0]) {
+                continue;
+            }
+            let mut end = i + n;
+            let mut repeats = 1;
+            loop {
+                let pause = chars.get(end).is_some_and(|c| " ，,、".contains(*c));
+                if n > 1 && !pause {
+                    break;
+                }
+                let next = if pause { end + 1 } else { end };
+                if chars.get(next..next + n) == Some(unit) {
+                    end = next + n;
+                    repeats += 1;
+                } else {
+                    break;
+                }
+            }
+            if repeats >= if n == 1 { 3 } else { 2 } {
+                out.extend_from_slice(unit);
+                if n == 1 && DOUBLED.contains(unit[0]) {
+                    out.push(unit[0]);
+                }
+                i = end;
+                continue 'outer;
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out.into_iter().collect()
+}
+
+// "the the" -> "the"，"I, I think" -> "I think"
+fn collapse_word_repeats(text: &str) -> String {
+    static WORD: OnceLock<Regex> = OnceLock::new();
+    let word = regex(&WORD, r"[A-Za-z][A-Za-z']*");
+
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut previous: Option<&str> = None;
+    for m in word.find_iter(text) {
+        let gap = &text[last..m.start()];
+        let repeated = previous.is_some_and(|p| p.eq_ignore_ascii_case(m.as_str()))
+            && gap.chars().all(|c| c == ' ' || c == ',');
+        if !repeated {
+            out.push_str(gap);
+            out.push_str(m.as_str());
+        }
+        previous = Some(m.as_str());
+        last = m.end();
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+fn remove_fillers(text: &str) -> String {
+    static CHINESE: OnceLock<Regex> = OnceLock::new();
+    static CHINESE_WEAK: OnceLock<Regex> = OnceLock::new();
+    static ENGLISH: OnceLock<Regex> = OnceLock::new();
+    static ENGLISH_WEAK: OnceLock<Regex> = OnceLock::new();
+
+    let text = regex(&CHINESE, CHINESE_FILLERS).replace_all(text, "");
+    // 英文按完整单词匹配，"umbrella" 里的 um 不受影响
+    let text = regex(&ENGLISH, r"[A-Za-z][A-Za-z']*[,，]?\s*").replace_all(&text, |caps: &regex::Captures| {
+        let token = &caps[0];
+        let word = token.trim_end_matches(|c: char| c == ',' || c == '，' || c.is_whitespace());
+        if ENGLISH_FILLERS.contains(&word.to_lowercase().as_str()) {
+            String::new()
+        } else {
+            token.to_string()
+        }
+    });
+    let text = regex(&ENGLISH_WEAK, ENGLISH_WEAK_FILLERS).replace_all(&text, " ");
+
+    // 删掉一个语气词后，后面的语气词才处在句首，反复替换直到不再变化
+    let weak = regex(&CHINESE_WEAK, CHINESE_WEAK_FILLERS);
+    let mut text = text.into_owned();
+    loop {
+        let replaced = weak.replace_all(&text, "$1");
+        if replaced == text {
+            return text;
+        }
+        text = replaced.into_owned();
+    }
+}
+
+// 删掉语气词后会留下 "，，"、"，。"、句首的逗号和标点前的空格
+fn tidy_punctuation(text: &str) -> String {
+    static SPACE_BEFORE: OnceLock<Regex> = OnceLock::new();
+    static COMMAS: OnceLock<Regex> = OnceLock::new();
+    static COMMA_BEFORE_END: OnceLock<Regex> = OnceLock::new();
+    static LEADING: OnceLock<Regex> = OnceLock::new();
+    static SPACES: OnceLock<Regex> = OnceLock::new();
+
+    let text = regex(&SPACE_BEFORE, r"[ \t]+([，。！？、；：,.!?;:])").replace_all(text, "$1");
+    let text = regex(&COMMAS, r"([，、,])(?:\s*[，、,])+").replace_all(&text, "$1");
+    let text = regex(&COMMA_BEFORE_END, r"[，、,]\s*([。！？.!?])").replace_all(&text, "$1");
+    let text = regex(&LEADING, r"(?m)^[\s，、,。.]+|([。！？.!?])[，、,]+").replace_all(&text, "$1");
+    let text = regex(&SPACES, r"[ \t]{2,}").replace_all(&text, " ");
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleans_transcripts() {
+        let cases = [
+            // 语气词
+            ("嗯，我想要一个排序函数", "我想要一个排序函数"),
+            ("呃呃我觉得可以", "我觉得可以"),
+            ("那个，我想问一下", "我想问一下"),
+            ("我们先这样，就是说，明天再改", "我们先这样，明天再改"),
+            ("um, I think it works", "I think it works"),
+            ("hmm, let me think", "let me think"),
+            ("it's, like, broken", "it's broken"),
+            // 口吃重复
+            ("我我我想要", "我想要"),
+            ("我，我，我想要", "我想要"),
+            ("我们，我们去吧", "我们去吧"),
+            ("这个，这个问题", "这个问题"),
+            ("谢谢谢谢", "谢谢"),
+            ("the the function", "the function"),
+            // 正常用法保持原样
+            ("那个人", "那个人"),
+            ("就是这样", "就是这样"),
+            ("研究研究", "研究研究"),
+            ("一个一个来", "一个一个来"),
+            ("看看这个", "看看这个"),
+            ("谢谢你", "谢谢你"),
+            ("umbrella", "umbrella"),
+            ("use a 5 mm screw", "use a 5 mm screw"),
+            ("er is short for emergency room", "er is short for emergency room"),
+            ("六六六", "六六六"),
+            ("哈哈哈", "哈哈哈"),
+            ("哈哈哈哈太好笑了", "哈哈哈哈太好笑了"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(clean(text), expected, "{}", text);
+        }
+    }
+}
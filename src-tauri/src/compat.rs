@@ -0,0 +1,256 @@
+use crate::audio::{self, AIConfig, PROCESS_AUDIO_PROMPT};
+use crate::error::{AppError, ErrorKind};
+use crate::retry::send_with_retry;
+use crate::stream::{self, DeltaSink};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// An OpenAI-compatible service, either built in or defined in the user config file.
+///
+/// Everything that differs between vendors (paths, auth, extra headers and body
+/// fields) is data here, so Groq, SiliconFlow, LM Studio, vLLM and the like only
+/// need an entry in `config.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompatProvider {
+    /// 服务商 id，也用于按服务商设置代理
+    pub id: String,
+    pub name: String,
+    /// 界面上的默认接口地址和模型，可在设置里覆盖
+    pub base_url: String,
+    pub whisper_model: String,
+    pub gpt_model: String,
+    /// 语音识别路径，为空表示不支持语音识别
+    pub transcription_path: Option<String>,
+    /// 对话补全路径，为空表示不支持文本格式化
+    pub chat_path: Option<String>,
+    /// 模型列表路径，为空表示不支持列出模型
+    pub models_path: Option<String>,
+    /// 携带 API Key 的请求头
+    pub auth_header: String,
+    /// API Key 前的认证方案，如 "Bearer"；为空时直接发送 API Key
+    pub auth_scheme: String,
+    /// 每个请求都附带的请求头
+    pub headers: BTreeMap<String, String>,
+    /// 合并进对话补全请求体的字段，如 max_tokens；同名时覆盖默认的 temperature
+    pub extra_body: Map<String, Value>,
+    /// 附加到语音识别表单的字段，同名时覆盖默认的 language、response_format
+    pub extra_form: BTreeMap<String, String>,
+}
+
+impl Default for CompatProvider {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            name: String::new(),
+            base_url: String::new(),
+            whisper_model: String::new(),
+            gpt_model: String::new(),
+            transcription_path: Some("/audio/transcriptions".to_string()),
+            chat_path: Some("/chat/completions".to_string()),
+            models_path: Some("/models".to_string()),
+            auth_header: "Authorization".to_string(),
+            auth_scheme: "Bearer".to_string(),
+            headers: BTreeMap::new(),
+            extra_body: Map::new(),
+            extra_form: BTreeMap::new(),
+        }
+    }
+}
+
+impl CompatProvider {
+    /// OpenAI and the vendors that mirror its API under their own base URL (DeepSeek, Zhipu, Moonshot).
+    pub fn openai() -> Self {
+        Self::default()
+    }
+
+    /// Ollama 的 OpenAI 兼容接口挂在 /v1 下，本地服务不需要鉴权；Ollama 不提供语音识别
+    pub fn ollama() -> Self {
+        Self {
+            transcription_path: None,
+            chat_path: Some("/v1/chat/completions".to_string()),
+            models_path: Some("/v1/models".to_string()),
+            ..Self::default()
+        }
+    }
+
+    fn url(&self, config: &AIConfig, path: &str) -> String {
+        let base_url = if config.base_url.is_empty() { &self.base_url } else { &config.base_url };
+        format!("{}/{}", base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder, api_key: &str) -> reqwest::RequestBuilder {
+        let mut request = request;
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        // 本地服务通常不设 API Key，这时不发送鉴权头
+        if api_key.is_empty() {
+            return request;
+        }
+        let value = if self.auth_scheme.is_empty() {
+            api_key.to_string()
+        } else {
+            format!("{} {}", self.auth_scheme, api_key)
+        };
+        request.header(&self.auth_header, value)
+    }
+}
+
+pub async fn transcribe(client: &reqwest::Client, file_path: String, config: AIConfig, provider: &CompatProvider) -> Result<String, AppError> {
+    let path = provider
+        .transcription_path
+        .as_deref()
+        .ok_or_else(|| AppError::new(ErrorKind::ProviderUnsupported, format!("{} does not support transcription", config.provider)))?;
+
+    // Read the audio file
+    let audio_data = std::fs::read(&file_path)
+        .map_err(|e| AppError::new(ErrorKind::InvalidAudio, format!("Failed to read audio file: {}", e)))?;
+
+    let mut fields = BTreeMap::new();
+    fields.insert("model".to_string(), config.whisper_model.clone());
+    fields.insert("language".to_string(), "zh".to_string());
+    fields.insert("response_format".to_string(), "json".to_string());
+    fields.extend(provider.extra_form.clone());
+
+    let url = provider.url(&config, path);
+    let response = send_with_retry(&config.retry, || {
+        // Create multipart form
+        let mut form = reqwest::multipart::Form::new();
+        for (name, value) in &fields {
+            form = form.text(name.clone(), value.clone());
+        }
+        let form = form.part("file", reqwest::multipart::Part::bytes(audio_data.clone())
+            .file_name("audio.wav")
+            .mime_str("audio/wav").unwrap());
+
+        provider
+            .authorize(client.post(&url), &config.api_key)
+            .timeout(config.timeout())
+            .multipart(form)
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+
+    let text = json["text"].as_str()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No text in response"))?
+        .to_string();
+
+    Ok(text)
+}
+
+pub async fn format_text(client: &reqwest::Client, text: String, config: AIConfig, provider: &CompatProvider, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let messages = serde_json::json!([
+        {
+            "role": "system",
+            "content": config.system_prompt
+        },
+        {
+            "role": "user",
+            "content": text
+        }
+    ]);
+
+    chat(client, &config, provider, messages, on_delta).await
+}
+
+/// Sends the recording as `input_audio` to an audio-capable chat model (gpt-4o-audio style).
+pub async fn process_audio(client: &reqwest::Client, file_path: &str, config: AIConfig, provider: &CompatProvider, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let audio_data = audio::inline_wav_base64(file_path)?;
+    let messages = serde_json::json!([
+        {
+            "role": "system",
+            "content": config.system_prompt
+        },
+        {
+            "role": "user",
+            "content": [
+                { "type": "text", "text": PROCESS_AUDIO_PROMPT },
+                { "type": "input_audio", "input_audio": { "data": audio_data, "format": "wav" } }
+            ]
+        }
+    ]);
+
+    chat(client, &config, provider, messages, on_delta).await
+}
+
+async fn chat(client: &reqwest::Client, config: &AIConfig, provider: &CompatProvider, messages: Value, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let path = provider
+        .chat_path
+        .as_deref()
+        .ok_or_else(|| AppError::new(ErrorKind::ProviderUnsupported, format!("{} does not support text formatting", config.provider)))?;
+
+    let streaming = on_delta.is_some();
+    let mut request_body = serde_json::json!({
+        "model": config.gpt_model,
+        "messages": messages,
+        "temperature": config.temperature,
+        "stream": streaming
+    });
+    if let Some(body) = request_body.as_object_mut() {
+        body.extend(provider.extra_body.clone());
+    }
+
+    let url = provider.url(config, path);
+    let response = send_with_retry(&config.retry, || {
+        provider
+            .authorize(client.post(&url), &config.api_key)
+            .timeout(config.request_timeout(streaming))
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    if let Some(on_delta) = on_delta {
+        return stream::collect_openai_sse(response, config.timeout(), on_delta).await;
+    }
+
+    let json: Value = response.json().await?;
+
+    let formatted_text = json["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No content in response"))?
+        .to_string();
+
+    Ok(formatted_text)
+}
+
+/// Lists the model ids from the provider's `/models` endpoint.
+pub async fn list_models(client: &reqwest::Client, config: &AIConfig, provider: &CompatProvider, timeout: Duration) -> Result<Vec<String>, AppError> {
+    let path = provider
+        .models_path
+        .as_deref()
+        .ok_or_else(|| AppError::new(ErrorKind::ProviderUnsupported, format!("{} does not support listing models", config.provider)))?;
+
+    let url = provider.url(config, path);
+    let response = provider
+        .authorize(client.get(&url), &config.api_key)
+        .timeout(timeout)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    let models = json["data"]
+        .as_array()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No data in response"))?
+        .iter()
+        .filter_map(|model| model["id"].as_str().map(str::to_string))
+        .collect();
+
+    Ok(models)
+}
@@ -0,0 +1,214 @@
+use crate::audio::AIConfig;
+use crate::error::{AppError, ErrorKind};
+use crate::jobs::{self, CancelOnDrop, JobStatus, PollPolicy, Transcript, Utterance};
+use crate::retry::{send_with_connect_retry, send_with_retry};
+use serde_json::Value;
+
+/// Transcribes with DashScope Paraformer file transcription (`config.whisper_model`, e.g. `paraformer-v2`).
+///
+/// The recording is uploaded to DashScope's temporary storage, then an async
+/// transcription task is submitted and polled until it finishes. Speaker
+/// diarization is enabled, so several speakers come back labelled.
+pub async fn transcribe(client: &reqwest::Client, file_path: String, config: AIConfig) -> Result<String, AppError> {
+    let audio_data = std::fs::read(&file_path)
+        .map_err(|e| AppError::new(ErrorKind::InvalidAudio, format!("Failed to read audio file: {}", e)))?;
+
+    let file_url = upload(client, &config, audio_data).await?;
+    let task_id = submit(client, &config, &file_url).await?;
+
+    // 只能取消仍在排队的任务，已开始的任务取消请求会被忽略
+    let guard = {
+        let client = client.clone();
+        let url = format!("{}/api/v1/tasks/{}/cancel", config.base_url, task_id);
+        let api_key = config.api_key.clone();
+        CancelOnDrop::new(async move {
+            let _ = client.post(&url).header("Authorization", format!("Bearer {}", api_key)).send().await;
+        })
+    };
+
+    let url = format!("{}/api/v1/tasks/{}", config.base_url, task_id);
+    let policy = PollPolicy::for_config(&config);
+    let transcription_url = jobs::poll_job(&task_id, &policy, || check(client, &config, &url)).await?;
+    guard.disarm();
+
+    let transcript = fetch_transcript(client, &config, &transcription_url).await?;
+
+    Ok(transcript.into_text())
+}
+
+// Uploads the file with a temporary OSS policy and returns its `oss://` URL (valid for 48 hours).
+async fn upload(client: &reqwest::Client, config: &AIConfig, audio_data: Vec<u8>) -> Result<String, AppError> {
+    let url = format!("{}/api/v1/uploads", config.base_url);
+    let response = send_with_retry(&config.retry, || {
+        client
+            .get(&url)
+            .timeout(config.timeout())
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .query(&[("action", "getPolicy"), ("model", config.whisper_model.as_str())])
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    let policy = &json["data"];
+    let field = |name: &str| {
+        policy[name]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| AppError::new(ErrorKind::ParseError, format!("No {} in upload policy", name)))
+    };
+    let upload_host = field("upload_host")?;
+    let key = format!("{}/audio.wav", field("upload_dir")?);
+    let access_key_id = field("oss_access_key_id")?;
+    let signature = field("signature")?;
+    let oss_policy = field("policy")?;
+    let object_acl = field("x_oss_object_acl")?;
+    let forbid_overwrite = field("x_oss_forbid_overwrite")?;
+
+    let response = send_with_connect_retry(&config.retry, || {
+        let form = reqwest::multipart::Form::new()
+            .text("OSSAccessKeyId", access_key_id.clone())
+            .text("Signature", signature.clone())
+            .text("policy", oss_policy.clone())
+            .text("key", key.clone())
+            .text("x-oss-object-acl", object_acl.clone())
+            .text("x-oss-forbid-overwrite", forbid_overwrite.clone())
+            .text("success_action_status", "200")
+            .part("file", reqwest::multipart::Part::bytes(audio_data.clone())
+                .file_name("audio.wav")
+                .mime_str("audio/wav").unwrap());
+
+        client
+            .post(&upload_host)
+            .timeout(config.timeout())
+            .multipart(form)
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    Ok(format!("oss://{}", key))
+}
+
+async fn submit(client: &reqwest::Client, config: &AIConfig, file_url: &str) -> Result<String, AppError> {
+    let request_body = serde_json::json!({
+        "model": config.whisper_model,
+        "input": { "file_urls": [file_url] },
+        "parameters": {
+            "language_hints": ["zh", "en"],
+            "diarization_enabled": true
+        }
+    });
+
+    let url = format!("{}/api/v1/services/audio/asr/transcription", config.base_url);
+    // 超时后重发会创建重复计费的任务，只在连接失败时重试
+    let response = send_with_connect_retry(&config.retry, || {
+        client
+            .post(&url)
+            .timeout(config.timeout())
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .header("Content-Type", "application/json")
+            .header("X-DashScope-Async", "enable")
+            // 让服务端解析上一步得到的 oss:// 临时地址
+            .header("X-DashScope-OssResourceResolve", "enable")
+            .json(&request_body)
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    json["output"]["task_id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No task_id in response"))
+}
+
+// Checks the task once; when it succeeded, returns the URL of the transcription result.
+async fn check(client: &reqwest::Client, config: &AIConfig, url: &str) -> Result<JobStatus<String>, AppError> {
+    let response = send_with_retry(&config.retry, || {
+        client
+            .get(url)
+            .timeout(config.timeout())
+            .header("Authorization", format!("Bearer {}", config.api_key))
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    let output = &json["output"];
+    match output["task_status"].as_str().unwrap_or_default() {
+        "SUCCEEDED" => {
+            // 每个文件有独立的子任务状态，任务整体成功时单个文件仍可能失败
+            let result = &output["results"][0];
+            if result["subtask_status"].as_str() == Some("FAILED") {
+                return Err(task_error(result));
+            }
+            result["transcription_url"]
+                .as_str()
+                .map(|url| JobStatus::Finished(url.to_string()))
+                .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No transcription_url in response"))
+        }
+        "FAILED" | "CANCELED" | "UNKNOWN" => Err(task_error(output)),
+        // PENDING / RUNNING
+        _ => Ok(JobStatus::Running),
+    }
+}
+
+async fn fetch_transcript(client: &reqwest::Client, config: &AIConfig, transcription_url: &str) -> Result<Transcript, AppError> {
+    // 结果地址是带签名的 OSS 链接，不需要鉴权头
+    let response = send_with_retry(&config.retry, || client.get(transcription_url).timeout(config.timeout())).await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    let transcripts = json["transcripts"]
+        .as_array()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No transcripts in response"))?;
+
+    // 每个声道一条转写结果，录音是单声道时只有一条
+    let text = transcripts.iter().filter_map(|t| t["text"].as_str()).collect();
+    let utterances = transcripts
+        .iter()
+        .filter_map(|t| t["sentences"].as_array())
+        .flatten()
+        .filter_map(|sentence| {
+            Some(Utterance {
+                speaker: sentence["speaker_id"].as_u64()?.to_string(),
+                text: sentence["text"].as_str()?.to_string(),
+            })
+        })
+        .collect();
+
+    Ok(Transcript { text, utterances })
+}
+
+fn task_error(output: &Value) -> AppError {
+    let code = output["code"].as_str().unwrap_or_default();
+    let message = output["message"].as_str().unwrap_or("Transcription task failed");
+
+    let kind = match code {
+        "InvalidApiKey" => ErrorKind::Auth,
+        "Throttling" | "Throttling.RateQuota" => ErrorKind::RateLimited,
+        "Arrearage" | "Throttling.AllocationQuota" => ErrorKind::QuotaExceeded,
+        "InvalidFile.DecodeFailed" | "InvalidFile.AudioLengthError" | "SUCCESS_WITH_NO_VALID_FRAGMENT" => ErrorKind::InvalidAudio,
+        _ => ErrorKind::ServerError,
+    };
+
+    AppError {
+        provider_message: Some(message.to_string()),
+        ..AppError::new(kind, format!("DashScope transcription failed ({}): {}", code, message))
+    }
+}
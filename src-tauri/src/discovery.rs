@@ -0,0 +1,264 @@
+use crate::audio::AIConfig;
+use crate::azure_auth;
+use crate::compat::{self, CompatProvider};
+use crate::error::{AppError, ErrorKind};
+use crate::ollama;
+use crate::realtime;
+use crate::tencent;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+// 列出模型是轻量请求，不需要等满转写用的超时时间
+const LIST_TIMEOUT: Duration = Duration::from_secs(15);
+
+// 列出部署的接口在 2023 年之后的数据面 API 版本里已移除，所填版本不支持时退回这个版本
+const AZURE_DEPLOYMENTS_API_VERSION: &str = "2022-12-01";
+
+// 本地引擎没有可以连接的服务
+const LOCAL_ENGINES: &[&str] = &["whisper-local", "llama-local", "vosk"];
+
+/// Diagnosis returned by `test_connection`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionReport {
+    pub ok: bool,
+    /// 本地引擎不做检查，此时为 false，其余字段没有意义
+    pub checked: bool,
+    /// 接口地址可以连上并返回了 HTTP 响应
+    pub reachable: bool,
+    /// API Key 已通过验证
+    pub authenticated: bool,
+    /// 所填模型是否在服务商的模型列表中，没有填写模型时为 None
+    pub model_found: Option<bool>,
+    /// 模型不存在时列出名称相近的模型
+    pub suggestions: Vec<String>,
+    pub latency_ms: u64,
+    pub error: Option<AppError>,
+}
+
+/// Lists the models (or Azure deployments) available with the configured key.
+pub async fn list_models(client: &reqwest::Client, config: &AIConfig) -> Result<Vec<String>, AppError> {
+    let mut models = match (&config.custom, config.provider.as_str()) {
+        // config.json 中的同名服务商覆盖内置的
+        (Some(provider), _) => compat::list_models(client, config, provider, LIST_TIMEOUT).await?,
+        (None, "openai" | "deepseek" | "zhipu" | "moonshot") => {
+            compat::list_models(client, config, &CompatProvider::openai(), LIST_TIMEOUT).await?
+        }
+        (None, "azure") => list_azure_deployments(client, config).await?,
+        (None, "anthropic") => list_anthropic_models(client, config).await?,
+        (None, "ollama") => ollama::list_models(client, &config.base_url)
+            .await?
+            .into_iter()
+            .map(|model| model.name)
+            .collect(),
+        (None, "gemini") => list_gemini_models(client, config).await?,
+        _ => {
+            return Err(AppError::new(
+                ErrorKind::ProviderUnsupported,
+                format!("{} does not support listing models", config.provider),
+            ))
+        }
+    };
+
+    models.sort();
+    models.dedup();
+    Ok(models)
+}
+
+/// Checks the key, base URL and `model` by listing the provider's models.
+///
+/// Providers without a model list get a cheap authenticated request instead and
+/// local engines are reported as not checked; none of this spends tokens.
+pub async fn test_connection(client: &reqwest::Client, config: &AIConfig, model: &str) -> ConnectionReport {
+    if LOCAL_ENGINES.contains(&config.provider.as_str()) {
+        return ConnectionReport {
+            ok: false,
+            checked: false,
+            reachable: false,
+            authenticated: false,
+            model_found: None,
+            suggestions: Vec::new(),
+            latency_ms: 0,
+            error: None,
+        };
+    }
+
+    let started = Instant::now();
+    // 没有模型列表的服务只检查接口地址和密钥，不检查模型
+    let result = match config.provider.as_str() {
+        _ if config.custom.is_some() => list_models(client, config).await.map(Some),
+        "dashscope" => probe_dashscope(client, config, model).await.map(|()| None),
+        "tencent" => tencent::probe(client, config).await.map(|()| None),
+        "assemblyai" => probe_assemblyai(client, config).await.map(|()| None),
+        "openai-realtime" => realtime::probe(config).await.map(|()| None),
+        _ => list_models(client, config).await.map(Some),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(models) => {
+            let model_found = models.as_ref().filter(|_| !model.is_empty()).map(|models| models.iter().any(|m| same_model(m, model)));
+            let (suggestions, error) = if model_found == Some(false) {
+                (
+                    similar_models(models.as_deref().unwrap_or_default(), model),
+                    Some(AppError::new(ErrorKind::InvalidRequest, format!("Model not found: {}", model))),
+                )
+            } else {
+                (Vec::new(), None)
+            };
+            ConnectionReport {
+                ok: error.is_none(),
+                checked: true,
+                reachable: true,
+                authenticated: true,
+                model_found,
+                suggestions,
+                latency_ms,
+                error,
+            }
+        }
+        Err(error) => ConnectionReport {
+            ok: false,
+            checked: true,
+            reachable: error.status.is_some(),
+            authenticated: false,
+            model_found: None,
+            suggestions: Vec::new(),
+            latency_ms,
+            error: Some(error),
+        },
+    }
+}
+
+async fn list_azure_deployments(client: &reqwest::Client, config: &AIConfig) -> Result<Vec<String>, AppError> {
+    let (auth_name, auth_value) = azure_auth::auth_header(client, config).await?;
+    let request = |api_version: &str| {
+        client
+            .get(format!("{}/openai/deployments", config.base_url))
+            .timeout(LIST_TIMEOUT)
+            .header(auth_name, &auth_value)
+            .query(&[("api-version", api_version)])
+            .send()
+    };
+
+    let api_version = config.api_version.as_deref().filter(|v| !v.is_empty()).unwrap_or(AZURE_DEPLOYMENTS_API_VERSION);
+    let mut response = request(api_version).await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND && api_version != AZURE_DEPLOYMENTS_API_VERSION {
+        response = request(AZURE_DEPLOYMENTS_API_VERSION).await?;
+    }
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    ids(&json["data"], "id")
+}
+
+async fn list_anthropic_models(client: &reqwest::Client, config: &AIConfig) -> Result<Vec<String>, AppError> {
+    let url = format!("{}/v1/models", config.base_url);
+    let response = client
+        .get(&url)
+        .timeout(LIST_TIMEOUT)
+        .header("x-api-key", &config.api_key)
+        .header("anthropic-version", "2023-06-01")
+        .query(&[("limit", "1000")])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    ids(&json["data"], "id")
+}
+
+async fn list_gemini_models(client: &reqwest::Client, config: &AIConfig) -> Result<Vec<String>, AppError> {
+    let url = format!("{}/models", config.base_url);
+    let response = client
+        .get(&url)
+        .timeout(LIST_TIMEOUT)
+        .header("x-goog-api-key", &config.api_key)
+        .query(&[("pageSize", "1000")])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    // 名称形如 models/gemini-2.0-flash，设置里填写的是去掉前缀的部分
+    let models = ids(&json["models"], "name")?
+        .into_iter()
+        .map(|name| name.trim_start_matches("models/").to_string())
+        .collect();
+    Ok(models)
+}
+
+// 申请临时上传凭证，需要有效的 API Key，不上传文件也不计费
+async fn probe_dashscope(client: &reqwest::Client, config: &AIConfig, model: &str) -> Result<(), AppError> {
+    let model = if model.is_empty() { "paraformer-v2" } else { model };
+    let response = client
+        .get(format!("{}/api/v1/uploads", config.base_url))
+        .timeout(LIST_TIMEOUT)
+        .header("Authorization", format!("Bearer {}", config.api_key))
+        .query(&[("action", "getPolicy"), ("model", model)])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+    Ok(())
+}
+
+async fn probe_assemblyai(client: &reqwest::Client, config: &AIConfig) -> Result<(), AppError> {
+    let response = client
+        .get(format!("{}/v2/transcript", config.base_url))
+        .timeout(LIST_TIMEOUT)
+        .header("Authorization", &config.api_key)
+        .query(&[("limit", "1")])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+    Ok(())
+}
+
+fn ids(list: &Value, field: &str) -> Result<Vec<String>, AppError> {
+    let ids = list
+        .as_array()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No model list in response"))?
+        .iter()
+        .filter_map(|item| item[field].as_str().map(str::to_string))
+        .collect();
+    Ok(ids)
+}
+
+// Ollama 不写标签时默认使用 latest
+fn same_model(listed: &str, model: &str) -> bool {
+    listed == model || listed.strip_suffix(":latest") == Some(model)
+}
+
+// 互相包含，或者第一个 - 之前的系列名相同（gpt-4o-mini 与 gpt-4o、whisper-1 与 whisper-large-v3）
+fn similar_models(models: &[String], model: &str) -> Vec<String> {
+    let wanted = model.to_lowercase();
+    let family = wanted.split(['-', ':', '/']).next().unwrap_or_default();
+
+    models
+        .iter()
+        .filter(|candidate| {
+            let candidate = candidate.to_lowercase();
+            candidate.contains(&wanted)
+                || wanted.contains(&candidate)
+                || (!family.is_empty() && candidate.split(['-', ':', '/']).next() == Some(family))
+        })
+        .take(5)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,235 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+/// 错误类别，前端据此决定提示文案和处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    Auth,
+    RateLimited,
+    QuotaExceeded,
+    Network,
+    Timeout,
+    InvalidAudio,
+    NoInputDevice,
+    ProviderUnsupported,
+    ParseError,
+    InvalidRequest,
+    ServerError,
+    Recording,
+    Clipboard,
+    LocalModel,
+    Config,
+    Cancelled,
+}
+
+impl ErrorKind {
+    pub fn hint(self) -> &'static str {
+        match self {
+            ErrorKind::Auth => "API Key 无效或没有权限，请在设置中检查 API Key",
+            ErrorKind::RateLimited => "请求过于频繁，请稍后再试",
+            ErrorKind::QuotaExceeded => "账户额度已用完，请检查服务商的余额或套餐",
+            ErrorKind::Network => "无法连接服务，请检查网络、代理设置和接口地址",
+            ErrorKind::Timeout => "服务响应超时，请稍后重试或调大超时时间",
+            ErrorKind::InvalidAudio => "音频无法识别，请确认录音时长和麦克风输入",
+            ErrorKind::NoInputDevice => "没有找到麦克风，请连接麦克风并检查系统权限",
+            ErrorKind::ProviderUnsupported => "当前服务商不支持该操作，请在设置中更换服务商",
+            ErrorKind::ParseError => "服务返回了无法解析的内容，请检查接口地址和模型名称",
+            ErrorKind::InvalidRequest => "请求被服务拒绝，请检查模型名称、部署名称和接口地址",
+            ErrorKind::ServerError => "服务端出现错误，请稍后重试",
+            ErrorKind::Recording => "录音失败，请检查麦克风是否被其他程序占用",
+            ErrorKind::Clipboard => "复制到剪贴板失败，可以手动复制结果",
+            ErrorKind::LocalModel => "本地模型不可用，请检查模型文件是否完整，或重新下载/导入模型",
+            ErrorKind::Config => "配置文件格式有误，请检查应用配置目录下的 config.json",
+            ErrorKind::Cancelled => "已取消",
+        }
+    }
+}
+
+// 服务商错误信息里表示音频本身有问题的说法；不能只匹配 "file"，否则 "file not found"、"profile" 也会命中
+const AUDIO_PHRASES: &[&str] = &[
+    "audio",
+    "file format",
+    "file type",
+    "unsupported file",
+    "invalid file",
+    "corrupted file",
+    "media type",
+];
+
+/// Error returned to the frontend by every command in the recording and provider pipeline.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// 服务商返回的 HTTP 状态码
+    pub status: Option<u16>,
+    /// 服务商错误体里的原始错误信息
+    pub provider_message: Option<String>,
+    pub hint: String,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            status: None,
+            provider_message: None,
+            hint: kind.hint().to_string(),
+        }
+    }
+
+    pub fn cancelled() -> Self {
+        Self::new(ErrorKind::Cancelled, "Request cancelled")
+    }
+
+    pub fn unsupported_provider(provider: &str) -> Self {
+        Self::new(ErrorKind::ProviderUnsupported, format!("Unsupported AI provider: {}", provider))
+    }
+
+    /// Builds an error from a non-success provider response, consuming its body.
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        Self::from_status(status, &body)
+    }
+
+    pub fn from_status(status: u16, body: &str) -> Self {
+        let (provider_message, code) = provider_error(body);
+        let detail = provider_message.as_deref().unwrap_or(body);
+        let lowered = format!("{} {}", code.as_deref().unwrap_or_default(), detail).to_lowercase();
+
+        let kind = match status {
+            401 | 403 => ErrorKind::Auth,
+            // Gemini 对无效的 API Key 返回 400
+            400 if lowered.contains("api key") || lowered.contains("api_key_invalid") => ErrorKind::Auth,
+            402 => ErrorKind::QuotaExceeded,
+            429 if lowered.contains("quota") || lowered.contains("billing") || lowered.contains("balance") => {
+                ErrorKind::QuotaExceeded
+            }
+            429 => ErrorKind::RateLimited,
+            408 | 504 => ErrorKind::Timeout,
+            // 只上传音频时才会遇到请求体过大
+            413 => ErrorKind::InvalidAudio,
+            400 | 415 | 422 if AUDIO_PHRASES.iter().any(|phrase| lowered.contains(phrase)) => ErrorKind::InvalidAudio,
+            400..=499 => ErrorKind::InvalidRequest,
+            _ => ErrorKind::ServerError,
+        };
+
+        let message = format!("API request failed ({}): {}", status, detail);
+        Self {
+            status: Some(status),
+            provider_message,
+            ..Self::new(kind, message)
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        let kind = if e.is_timeout() {
+            ErrorKind::Timeout
+        } else if e.is_decode() {
+            ErrorKind::ParseError
+        } else {
+            ErrorKind::Network
+        };
+
+        let message = if e.is_decode() {
+            format!("Failed to parse response: {}", e)
+        } else {
+            format!("Failed to send request: {}", e)
+        };
+
+        Self {
+            status: e.status().map(|s| s.as_u16()),
+            ..Self::new(kind, message)
+        }
+    }
+}
+
+// Pulls the human-readable message and error code out of the provider's error body.
+// Covers OpenAI/Azure/Anthropic/Gemini ({"error": {"message", "code"|"type"|"status"}}) Ollama ({"error": "..."})
+// and DashScope ({"code", "message"}).
+fn provider_error(body: &str) -> (Option<String>, Option<String>) {
+    let json: Value = match serde_json::from_str(body) {
+        Ok(json) => json,
+        Err(_) => return (None, None),
+    };
+
+    let error = &json["error"];
+    if let Some(message) = error.as_str() {
+        return (Some(message.to_string()), None);
+    }
+
+    let message = error["message"]
+        .as_str()
+        .or_else(|| json["message"].as_str())
+        .map(str::to_string);
+    let code = error["code"]
+        .as_str()
+        .or_else(|| error["type"].as_str())
+        .or_else(|| error["status"].as_str())
+        .or_else(|| json["code"].as_str())
+        .map(str::to_string);
+    (message, code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_status_codes() {
+        let cases = [
+            (400, r#"{"error":{"message":"Invalid model name"}}"#, ErrorKind::InvalidRequest),
+            (400, r#"{"error":{"message":"API key not valid","status":"INVALID_ARGUMENT"}}"#, ErrorKind::Auth),
+            (400, r#"{"error":{"message":"Invalid file format. Supported formats: flac, mp3, wav"}}"#, ErrorKind::InvalidAudio),
+            (400, r#"{"error":{"message":"Audio file is too short"}}"#, ErrorKind::InvalidAudio),
+            (400, r#"{"error":{"message":"file not found"}}"#, ErrorKind::InvalidRequest),
+            (400, r#"{"error":{"message":"profile is missing"}}"#, ErrorKind::InvalidRequest),
+            (401, r#"{"error":{"message":"Incorrect API key provided"}}"#, ErrorKind::Auth),
+            (403, "Forbidden", ErrorKind::Auth),
+            (404, r#"{"error":{"message":"The model does not exist"}}"#, ErrorKind::InvalidRequest),
+            (404, r#"{"error":{"message":"file not found"}}"#, ErrorKind::InvalidRequest),
+            (413, "Request Entity Too Large", ErrorKind::InvalidAudio),
+            (429, r#"{"error":{"message":"Rate limit reached"}}"#, ErrorKind::RateLimited),
+            (429, r#"{"error":{"message":"You exceeded your current quota","code":"insufficient_quota"}}"#, ErrorKind::QuotaExceeded),
+            (500, "Internal Server Error", ErrorKind::ServerError),
+            (502, "Bad Gateway", ErrorKind::ServerError),
+            (503, r#"{"error":{"message":"The engine is currently overloaded"}}"#, ErrorKind::ServerError),
+            (504, "Gateway Timeout", ErrorKind::Timeout),
+        ];
+        for (status, body, expected) in cases {
+            let error = AppError::from_status(status, body);
+            assert_eq!(error.kind, expected, "{} {}", status, body);
+            assert_eq!(error.status, Some(status));
+        }
+    }
+
+    #[test]
+    fn extracts_provider_message() {
+        let cases = [
+            (r#"{"error":{"message":"bad key","code":"invalid_api_key"}}"#, Some("bad key"), Some("invalid_api_key")),
+            (r#"{"error":"model not found"}"#, Some("model not found"), None),
+            (r#"{"code":"InvalidParameter","message":"url error"}"#, Some("url error"), Some("InvalidParameter")),
+            ("<html>502</html>", None, None),
+        ];
+        for (body, message, code) in cases {
+            let (actual_message, actual_code) = provider_error(body);
+            assert_eq!(actual_message.as_deref(), message, "{}", body);
+            assert_eq!(actual_code.as_deref(), code, "{}", body);
+        }
+    }
+}
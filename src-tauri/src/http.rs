@@ -0,0 +1,128 @@
+use crate::error::{AppError, ErrorKind};
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// 网络设置：代理、自定义根证书、连接超时
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkConfig {
+    /// 所有服务商默认使用的代理，支持 http://、https://、socks5://、socks5h://
+    pub proxy: Option<String>,
+    /// 按服务商覆盖代理，key 为服务商 id；值为 "direct" 表示该服务商直连
+    pub provider_proxies: HashMap<String, String>,
+    /// 始终直连的主机，例如本地的 Ollama
+    pub no_proxy: Vec<String>,
+    /// 额外信任的根证书文件（PEM 或 DER）
+    pub ca_certificates: Vec<String>,
+    pub connect_timeout_ms: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            provider_proxies: HashMap::new(),
+            no_proxy: vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()],
+            ca_certificates: Vec::new(),
+            connect_timeout_ms: 10_000,
+        }
+    }
+}
+
+/// Pooled HTTP clients shared by every provider call.
+///
+/// Proxies are a client-level setting in reqwest, so there is one client for
+/// direct connections plus one per distinct proxy URL. Cloning a `Client` is
+/// cheap and shares its connection pool.
+pub struct HttpClients {
+    config: NetworkConfig,
+    direct: Client,
+    proxied: HashMap<String, Client>,
+}
+
+impl HttpClients {
+    pub fn new(config: NetworkConfig) -> Result<Self, AppError> {
+        let certificates = load_certificates(&config.ca_certificates)?;
+
+        let direct = build_client(&config, &certificates, None)?;
+        let mut proxied = HashMap::new();
+        for proxy_url in config.proxy.iter().chain(config.provider_proxies.values()) {
+            if proxy_url == "direct" || proxy_url.is_empty() || proxied.contains_key(proxy_url) {
+                continue;
+            }
+            let client = build_client(&config, &certificates, Some(proxy_url))?;
+            proxied.insert(proxy_url.clone(), client);
+        }
+
+        Ok(Self { config, direct, proxied })
+    }
+
+    pub fn config(&self) -> &NetworkConfig {
+        &self.config
+    }
+
+    pub fn for_provider(&self, provider: &str) -> Client {
+        let proxy_url = match self.config.provider_proxies.get(provider) {
+            Some(url) => Some(url),
+            None => self.config.proxy.as_ref(),
+        };
+
+        proxy_url
+            .and_then(|url| self.proxied.get(url))
+            .unwrap_or(&self.direct)
+            .clone()
+    }
+}
+
+impl Default for HttpClients {
+    fn default() -> Self {
+        Self::new(NetworkConfig::default()).expect("Failed to create default HTTP client")
+    }
+}
+
+fn build_client(
+    config: &NetworkConfig,
+    certificates: &[Certificate],
+    proxy_url: Option<&String>,
+) -> Result<Client, AppError> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+        .pool_idle_timeout(Duration::from_secs(90));
+
+    for certificate in certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+
+    if let Some(proxy_url) = proxy_url {
+        let proxy = Proxy::all(proxy_url.as_str())
+            .map_err(|e| AppError::new(ErrorKind::Config, format!("Invalid proxy URL {}: {}", proxy_url, e)))?
+            .no_proxy(NoProxy::from_string(&config.no_proxy.join(",")));
+        builder = builder.proxy(proxy);
+    }
+
+    builder
+        .build()
+        .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to create HTTP client: {}", e)))
+}
+
+fn load_certificates(paths: &[String]) -> Result<Vec<Certificate>, AppError> {
+    let mut certificates = Vec::new();
+    for path in paths {
+        let data = std::fs::read(path)
+            .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to read certificate {}: {}", path, e)))?;
+
+        // PEM 文件可能包含整条证书链
+        if data.starts_with(b"-----BEGIN") {
+            let bundle = Certificate::from_pem_bundle(&data)
+                .map_err(|e| AppError::new(ErrorKind::Config, format!("Invalid PEM certificate {}: {}", path, e)))?;
+            certificates.extend(bundle);
+        } else {
+            let certificate = Certificate::from_der(&data)
+                .map_err(|e| AppError::new(ErrorKind::Config, format!("Invalid DER certificate {}: {}", path, e)))?;
+            certificates.push(certificate);
+        }
+    }
+    Ok(certificates)
+}
@@ -0,0 +1,405 @@
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+// 数字字符：N 可以带位数（二十五、一万二），D 只有逐位读的数字（二零二六、三点一四 的小数部分）
+const N: &str = "[零〇一二两三四五六七八九十百千万亿]";
+const D: &str = "[零〇一二三四五六七八九幺]";
+
+// 这些量词前的数字不小于十，或者是小数时才改写，"三个人"、"一下" 保持原样
+// "字"、"万"、"年"、"步" 不在其中："十字路口"、"十万火急"、"十年磨一剑"、"五十步笑百步" 不是数量
+const MEASURES: &str = "个月|分钟|小时|个|次|位|名|台|条|行|页|秒|天|周|岁|人|倍|张|篇|本|项|件|份|种|组|米|克|度|层|遍";
+
+/// Inverse text normalization for Chinese transcripts: spoken numerals in
+/// dates, times, percentages, money and units become written form, e.g.
+/// "二零二六年十月十七号" -> "2026年10月17日", "三点五个G" -> "3.5GB".
+///
+/// Numerals outside those contexts are only rewritten before a measure word
+/// and when the number is at least ten, so idioms and "一个" stay as they are.
+pub fn normalize(text: &str) -> String {
+    static FIXED: OnceLock<Regex> = OnceLock::new();
+
+    // 术语里的数字不是数量，整段跳过："一元二次方程" 不是 "1元二次方程"
+    let fixed = regex(&FIXED, || format!("{N}元{N}次"));
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for m in fixed.find_iter(text) {
+        out.push_str(&normalize_numbers(&text[last..m.start()]));
+        out.push_str(m.as_str());
+        last = m.end();
+    }
+    out.push_str(&normalize_numbers(&text[last..]));
+    out
+}
+
+fn normalize_numbers(text: &str) -> String {
+    let text = percentages(text);
+    let text = dates(&text);
+    let text = times(&text);
+    let text = money(&text);
+    let text = units(&text);
+    measures(&text)
+}
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: impl FnOnce() -> String) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(&pattern()).unwrap())
+}
+
+// 回调返回 None 时保留原文
+fn replace(re: &Regex, text: &str, f: impl Fn(&Captures) -> Option<String>) -> String {
+    re.replace_all(text, |caps: &Captures| f(caps).unwrap_or_else(|| caps[0].to_string()))
+        .into_owned()
+}
+
+fn percentages(text: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = regex(&RE, || format!("(百|千)分之({N}+)(?:点({D}+))?"));
+    replace(re, text, |caps| {
+        let value = decimal(&caps[2], caps.get(3).map(|m| m.as_str()))?;
+        Some(format!("{}{}", value, if &caps[1] == "百" { "%" } else { "‰" }))
+    })
+}
+
+fn dates(text: &str) -> String {
+    static YEAR: OnceLock<Regex> = OnceLock::new();
+    static DATE: OnceLock<Regex> = OnceLock::new();
+
+    // 年份逐位读："二零二六年"；"十年"、"两年" 是时长，不在这里处理。
+    // 两位的 "一九年" 也可能是 "三五年后" 这样的约数，后面跟着月份才算年份
+    let year = regex(&YEAR, || format!("({D}{{2,4}})年((?:十[一二]?|[一二三四五六七八九])月)?"));
+    let text = replace(year, text, |caps| {
+        let month = caps.get(2).map_or("", |m| m.as_str());
+        if caps[1].chars().count() < 4 && month.is_empty() {
+            return None;
+        }
+        Some(format!("{}年{}", digits(&caps[1])?, month))
+    });
+
+    // 月份只在前面有年份或后面有日期时改写，"五月天" 保持原样；"号" 统一写作 "日"
+    let date = regex(&DATE, || format!("([0-9]{{2,4}}年)?(十[一二]?|[一二三四五六七八九])月(?:({N}{{1,3}})[日号])?"));
+    replace(date, &text, |caps| {
+        let year = caps.get(1).map_or("", |m| m.as_str());
+        if year.is_empty() && caps.get(3).is_none() {
+            return None;
+        }
+        let month = integer(&caps[2]).filter(|m| (1..=12).contains(m))?;
+        match caps.get(3) {
+            Some(day) => {
+                let day = integer(day.as_str()).filter(|d| (1..=31).contains(d))?;
+                Some(format!("{}{}月{}日", year, month, day))
+            }
+            None => Some(format!("{}{}月", year, month)),
+        }
+    })
+}
+
+fn times(text: &str) -> String {
+    static CLOCK: OnceLock<Regex> = OnceLock::new();
+    static HOUR: OnceLock<Regex> = OnceLock::new();
+
+    // "三点二十分" -> "3:20"，"三点半" -> "3:30"；没有 "分" 的 "三点五" 和 "一点五分钟" 是小数
+    let clock = regex(&CLOCK, || format!("({N}{{1,3}})点(?:({N}{{1,3}})分(钟)?|(半)|钟)"));
+    let text = replace(clock, text, |caps| {
+        if caps.get(3).is_some() {
+            return None;
+        }
+        let hour = integer(&caps[1]).filter(|h| *h <= 24)?;
+        let minute = match (caps.get(2), caps.get(4)) {
+            (Some(minute), _) => integer(minute.as_str()).filter(|m| *m < 60)?,
+            (None, Some(_)) => 30,
+            (None, None) => return Some(format!("{}点", hour)),
+        };
+        Some(format!("{}:{:02}", hour, minute))
+    });
+
+    // 单独的 "三点" 也可能是 "三点建议"，只在前面有时段时改写
+    let hour = regex(&HOUR, || format!("(凌晨|早上|上午|中午|下午|傍晚|晚上)({N}{{1,3}})点"));
+    replace(hour, &text, |caps| {
+        let hour = integer(&caps[2]).filter(|h| *h <= 24)?;
+        Some(format!("{}{}点", &caps[1], hour))
+    })
+}
+
+fn money(text: &str) -> String {
+    static YUAN: OnceLock<Regex> = OnceLock::new();
+    static JIAO: OnceLock<Regex> = OnceLock::new();
+    static FOREIGN: OnceLock<Regex> = OnceLock::new();
+
+    // "三十块钱"、"三块五"、"两块五毛"、"十元"；单独的 "三块" 可能是量词，不改写。
+    // "三块五" 后面紧跟汉字时也可能是量词（"一块一块地"），要带 "毛"、"角"、"钱" 才算金额
+    let yuan = regex(&YUAN, || format!(r"({N}+)(?:点({D}+))?(?:块钱|元|块({D})(毛钱?|角|钱)?(\p{{Han}})?)"));
+    let text = replace(yuan, text, |caps| {
+        if caps.get(3).is_some() && caps.get(4).is_none() && caps.get(5).is_some() {
+            return None;
+        }
+        let fraction = caps.get(2).or(caps.get(3)).map(|m| m.as_str());
+        let next = caps.get(5).map_or("", |m| m.as_str());
+        Some(format!("{}元{}", decimal(&caps[1], fraction)?, next))
+    });
+
+    let jiao = regex(&JIAO, || format!("({D})(?:毛|角)钱"));
+    let text = replace(jiao, &text, |caps| Some(format!("0.{}元", digits(&caps[1])?)));
+
+    let foreign = regex(&FOREIGN, || format!("({N}+)(?:点({D}+))?(美元|美金|欧元|日元|英镑|港元|港币)"));
+    replace(foreign, &text, |caps| {
+        let value = decimal(&caps[1], caps.get(2).map(|m| m.as_str()))?;
+        Some(format!("{}{}", value, &caps[3]))
+    })
+}
+
+fn units(text: &str) -> String {
+    static STORAGE: OnceLock<Regex> = OnceLock::new();
+    static METRIC: OnceLock<Regex> = OnceLock::new();
+
+    // "三点五个G" -> "3.5GB"；只认大写字母，"三百m" 是米。
+    // "兆" 既可能是 MB 也可能是带宽的 Mbps，只写作 "M"
+    // 后面的字符要一起取出来："三个GPU" 不是容量，不带 B 的字母后面是汉字时（"三个T恤"）也不是
+    let storage = regex(&STORAGE, || format!(r"({N}+)(?:点({D}+))?个?(兆|吉|[KMGT])(B?)([A-Za-z]|\p{{Han}})?"));
+    let text = replace(storage, text, |caps| {
+        match caps.get(5).map(|m| m.as_str()) {
+            Some(next) if next.is_ascii() => return None,
+            Some(_) if caps[4].is_empty() && caps[3].is_ascii() => return None,
+            _ => {}
+        }
+        let unit = match &caps[3] {
+            "K" => "KB",
+            "兆" if caps[4].is_empty() => "M",
+            "M" | "兆" => "MB",
+            "G" | "吉" => "GB",
+            _ => "TB",
+        };
+        let next = caps.get(5).map_or("", |m| m.as_str());
+        Some(format!("{}{}{}", decimal(&caps[1], caps.get(2).map(|m| m.as_str()))?, unit, next))
+    });
+
+    let metric = regex(&METRIC, || format!("({N}+)(?:点({D}+))?(毫秒|兆赫|吉赫|赫兹|公里|千米|公斤|千克|厘米|毫米)"));
+    replace(metric, &text, |caps| {
+        let unit = match &caps[3] {
+            "毫秒" => "ms",
+            "兆赫" => "MHz",
+            "吉赫" => "GHz",
+            "赫兹" => "Hz",
+            "公里" | "千米" => "km",
+            "公斤" | "千克" => "kg",
+            "厘米" => "cm",
+            _ => "mm",
+        };
+        Some(format!("{}{}", decimal(&caps[1], caps.get(2).map(|m| m.as_str()))?, unit))
+    })
+}
+
+fn measures(text: &str) -> String {
+    static MEASURE: OnceLock<Regex> = OnceLock::new();
+    static ORDINAL: OnceLock<Regex> = OnceLock::new();
+
+    let measure = regex(&MEASURE, || format!("({N}+)(?:点({D}+))?({MEASURES})"));
+    let text = replace(measure, text, |caps| {
+        let fraction = caps.get(2).map(|m| m.as_str());
+        // "三万人" 写作 "3万人"，不展开成 30000
+        let (number, suffix) = match caps[1].strip_suffix(['万', '亿']) {
+            Some(prefix) if !prefix.is_empty() && fraction.is_none() => (prefix, &caps[1][prefix.len()..]),
+            _ => (&caps[1], ""),
+        };
+        if fraction.is_none() && suffix.is_empty() && integer(number)? < 10 {
+            return None;
+        }
+        // 没有 "十"、"百" 的多位数字是约数："三五年"、"七八个"
+        if number.chars().count() > 1 && number.chars().all(|c| digit(c).is_some()) {
+            return None;
+        }
+        Some(format!("{}{}{}", decimal(number, fraction)?, suffix, &caps[3]))
+    });
+
+    let ordinal = regex(&ORDINAL, || format!("第({N}+)"));
+    replace(ordinal, &text, |caps| {
+        let value = integer(&caps[1]).filter(|v| *v >= 10)?;
+        Some(format!("第{}", value))
+    })
+}
+
+fn digit(c: char) -> Option<u64> {
+    Some(match c {
+        '零' | '〇' => 0,
+        '一' | '幺' => 1,
+        '二' | '两' => 2,
+        '三' => 3,
+        '四' => 4,
+        '五' => 5,
+        '六' => 6,
+        '七' => 7,
+        '八' => 8,
+        '九' => 9,
+        _ => return None,
+    })
+}
+
+// 逐位读的数字，保留前导零："二零二六" -> "2026"
+fn digits(s: &str) -> Option<String> {
+    s.chars().map(|c| digit(c).map(|d| char::from(b'0' + d as u8))).collect()
+}
+
+/// Value of a Chinese numeral, including colloquial forms such as "一百五" (150)
+/// and "一万二" (12000). Digit sequences such as "二零二六" are read digit by digit.
+fn integer(s: &str) -> Option<u64> {
+    if s.is_empty() {
+        return None;
+    }
+    if s.chars().all(|c| digit(c).is_some()) {
+        return digits(s)?.parse().ok();
+    }
+
+    let mut total = 0; // 亿以上
+    let mut wan = 0; // 万到亿之间
+    let mut section = 0; // 万以下
+    let mut number: Option<u64> = None;
+    let mut last_unit = 1;
+    let mut zero = false;
+
+    for c in s.chars() {
+        match c {
+            '零' | '〇' => zero = true,
+            '十' | '百' | '千' => {
+                let unit = match c {
+                    '十' => 10,
+                    '百' => 100,
+                    _ => 1000,
+                };
+                // 开头的 "十" 表示一十
+                let n = match number.take() {
+                    Some(n) => n,
+                    None if unit == 10 && section == 0 => 1,
+                    None => return None,
+                };
+                section += n * unit;
+                last_unit = unit;
+                zero = false;
+            }
+            '万' | '亿' => {
+                section += number.take().unwrap_or(0);
+                if c == '万' {
+                    if section == 0 {
+                        return None;
+                    }
+                    wan += section * 10_000;
+                    last_unit = 10_000;
+                } else {
+                    if section + wan == 0 {
+                        return None;
+                    }
+                    total += (wan + section) * 100_000_000;
+                    wan = 0;
+                    last_unit = 100_000_000;
+                }
+                section = 0;
+                zero = false;
+            }
+            _ => {
+                if number.is_some() {
+                    return None;
+                }
+                number = Some(digit(c)?);
+            }
+        }
+    }
+
+    // 单位后面直接跟数字是省略了下一级单位："一百五" 是 150，"一百零五" 是 105
+    let tail = match number {
+        Some(n) if !zero && last_unit >= 10 => n * last_unit / 10,
+        Some(n) => n,
+        None => 0,
+    };
+    Some(total + wan + section + tail)
+}
+
+fn decimal(int: &str, fraction: Option<&str>) -> Option<String> {
+    let int = integer(int)?;
+    match fraction {
+        Some(fraction) => Some(format!("{}.{}", int, digits(fraction)?)),
+        None => Some(int.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize;
+
+    const CORPUS: &[(&str, &str)] = &[
+        // 日期
+        ("二零二六年十月十七号", "2026年10月17日"),
+        ("会议定在二零二五年三月", "会议定在2025年3月"),
+        ("十二月三十一日截止", "12月31日截止"),
+        ("一九年三月的版本", "19年3月的版本"),
+        ("三五年后再说", "三五年后再说"),
+        ("七八年的时间", "七八年的时间"),
+        ("五月天的演唱会", "五月天的演唱会"),
+        // 时间
+        ("下午三点二十分开会", "下午3:20开会"),
+        ("三点半出发", "3:30出发"),
+        ("早上八点到公司", "早上8点到公司"),
+        ("十点零五分", "10:05"),
+        ("一点五分钟", "1.5分钟"),
+        ("我有三点建议", "我有三点建议"),
+        // 百分比
+        ("百分之三十", "30%"),
+        ("增长了百分之十二点五", "增长了12.5%"),
+        ("千分之五", "5‰"),
+        // 金额
+        ("三十块钱", "30元"),
+        ("三块五", "3.5元"),
+        ("两块五毛", "2.5元"),
+        ("五毛钱", "0.5元"),
+        ("一百二十元", "120元"),
+        ("二十美元", "20美元"),
+        ("三块五一斤", "三块五一斤"),
+        ("三块五毛一斤", "3.5元一斤"),
+        ("三块五。", "3.5元。"),
+        ("切三块蛋糕", "切三块蛋糕"),
+        ("一块一块地拼起来", "一块一块地拼起来"),
+        // 单位
+        ("三点五个G", "3.5GB"),
+        ("两个TB的硬盘", "2TB的硬盘"),
+        ("三个G", "3GB"),
+        ("五百兆带宽", "500M带宽"),
+        ("两百兆的文件", "200M的文件"),
+        ("延迟二十毫秒", "延迟20ms"),
+        ("跑了十公里", "跑了10km"),
+        ("三个GPU", "三个GPU"),
+        ("三个T恤", "三个T恤"),
+        ("跑了三百m", "跑了三百m"),
+        // 带量词的数字
+        ("一百五十个用户", "150个用户"),
+        ("一百五个人", "150个人"),
+        ("一百零五个", "105个"),
+        ("一万二千行代码", "12000行代码"),
+        ("三万人", "3万人"),
+        ("一点五倍", "1.5倍"),
+        ("第十五页", "第15页"),
+        ("二十分钟", "20分钟"),
+        // 保持原样
+        ("一个人", "一个人"),
+        ("等一下", "等一下"),
+        ("十分重要", "十分重要"),
+        ("千万不要", "千万不要"),
+        ("三个问题", "三个问题"),
+        ("十几个", "十几个"),
+        ("七八个人", "七八个人"),
+        ("十字路口", "十字路口"),
+        ("十万火急", "十万火急"),
+        ("十年磨一剑", "十年磨一剑"),
+        ("五十步笑百步", "五十步笑百步"),
+        ("解一元二次方程", "解一元二次方程"),
+        ("二元一次方程组有两个解", "二元一次方程组有两个解"),
+        ("一元二次方程要十分钟", "一元二次方程要10分钟"),
+    ];
+
+    #[test]
+    fn corpus() {
+        let failures: Vec<String> = CORPUS
+            .iter()
+            .filter_map(|(input, expected)| {
+                let actual = normalize(input);
+                (actual != *expected).then(|| format!("{} -> {} (expected {})", input, actual, expected))
+            })
+            .collect();
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::audio::AIConfig;
+use crate::error::{AppError, ErrorKind};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+// 排队中的任务可能要等几十秒，总时长不少于两分钟
+const MIN_JOB_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 轮询间隔从 `initial_interval` 开始按 1.5 倍增长，直到 `max_interval`
+#[derive(Debug, Clone)]
+pub struct PollPolicy {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub timeout: Duration,
+}
+
+impl PollPolicy {
+    pub fn for_config(config: &AIConfig) -> Self {
+        Self {
+            initial_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(5),
+            timeout: config.timeout().max(MIN_JOB_TIMEOUT),
+        }
+    }
+
+    fn next_interval(&self, interval: Duration) -> Duration {
+        interval.mul_f32(1.5).min(self.max_interval)
+    }
+}
+
+/// Result of one status check.
+pub enum JobStatus<T> {
+    Running,
+    Finished(T),
+}
+
+/// Calls `check` until it reports the job finished, fails, or `policy.timeout` passes.
+pub async fn poll_job<T, F, Fut>(job_id: &str, policy: &PollPolicy, mut check: F) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<JobStatus<T>, AppError>>,
+{
+    let deadline = Instant::now() + policy.timeout;
+    let mut interval = policy.initial_interval;
+
+    loop {
+        if let JobStatus::Finished(result) = check().await? {
+            return Ok(result);
+        }
+
+        if Instant::now() + interval > deadline {
+            return Err(AppError::new(ErrorKind::Timeout, format!("Transcription job {} did not finish in time", job_id)));
+        }
+        tokio::time::sleep(interval).await;
+        interval = policy.next_interval(interval);
+    }
+}
+
+/// Cancels a remote job when dropped, unless [`CancelOnDrop::disarm`] was called.
+///
+/// Dropping happens on errors, timeouts and when the user cancels (the command
+/// future is dropped), so the service stops working on a result nobody will read.
+pub struct CancelOnDrop {
+    cancel: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+}
+
+impl CancelOnDrop {
+    pub fn new<F>(cancel: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        Self { cancel: Some(Box::pin(cancel)) }
+    }
+
+    pub fn disarm(mut self) {
+        self.cancel = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            // Drop 不能 await，交给运行时在后台发送取消请求
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(cancel);
+            }
+        }
+    }
+}
+
+/// 带说话人信息的一段转写
+#[derive(Debug, Clone)]
+pub struct Utterance {
+    pub speaker: String,
+    pub text: String,
+}
+
+/// Transcript of a job; `utterances` is only filled when the service diarized the audio.
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    pub text: String,
+    pub utterances: Vec<Utterance>,
+}
+
+impl Transcript {
+    /// Renders the transcript as the plain text the rest of the pipeline expects.
+    ///
+    /// With more than one speaker every utterance goes on its own line with a label;
+    /// a single speaker gets the undecorated text.
+    pub fn into_text(self) -> String {
+        let mut speakers: Vec<&str> = self.utterances.iter().map(|u| u.speaker.as_str()).collect();
+        speakers.sort_unstable();
+        speakers.dedup();
+        if speakers.len() <= 1 {
+            return self.text;
+        }
+
+        self.utterances
+            .iter()
+            .map(|u| format!("说话人{}：{}", u.speaker, u.text.trim()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utterance(speaker: &str, text: &str) -> Utterance {
+        Utterance { speaker: speaker.to_string(), text: text.to_string() }
+    }
+
+    #[test]
+    fn poll_intervals() {
+        let policy = PollPolicy::for_config(&AIConfig::default());
+        let mut interval = policy.initial_interval;
+        let mut intervals = Vec::new();
+        for _ in 0..8 {
+            intervals.push(interval.as_millis());
+            interval = policy.next_interval(interval);
+        }
+        assert_eq!(intervals, [500, 750, 1125, 1687, 2531, 3796, 5000, 5000]);
+    }
+
+    #[test]
+    fn job_timeout_floor() {
+        let cases = [(10_000, 120), (120_000, 120), (300_000, 300)];
+        for (timeout_ms, expected) in cases {
+            let config = AIConfig { timeout_ms, ..AIConfig::default() };
+            assert_eq!(PollPolicy::for_config(&config).timeout, Duration::from_secs(expected), "{}", timeout_ms);
+        }
+    }
+
+    #[test]
+    fn transcript_text() {
+        let cases = [
+            (Transcript { text: "你好".to_string(), utterances: vec![] }, "你好"),
+            (
+                Transcript { text: "你好 再见".to_string(), utterances: vec![utterance("A", "你好"), utterance("A", "再见")] },
+                "你好 再见",
+            ),
+            (
+                Transcript {
+                    text: "你好 在吗 在的".to_string(),
+                    utterances: vec![utterance("A", " 你好 "), utterance("B", "在吗"), utterance("A", "在的")],
+                },
+                "说话人A：你好\n说话人B：在吗\n说话人A：在的",
+            ),
+        ];
+        for (transcript, expected) in cases {
+            assert_eq!(transcript.into_text(), expected);
+        }
+    }
+}
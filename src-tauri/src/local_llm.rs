@@ -0,0 +1,157 @@
+use crate::audio::AIConfig;
+use crate::error::{AppError, ErrorKind};
+use crate::stream::DeltaSink;
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+use llama_cpp_2::sampling::LlamaSampler;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::mpsc;
+
+/// 未配置时的上下文长度，足够容纳一段语音转写和清理结果
+pub const DEFAULT_CONTEXT_SIZE: u32 = 4096;
+
+// llama.cpp 的后端只能初始化一次
+static BACKEND: OnceLock<LlamaBackend> = OnceLock::new();
+// 加载 GGUF 模型需要数秒，缓存最近一次使用的模型
+static MODEL: Mutex<Option<(String, Arc<LlamaModel>)>> = Mutex::new(None);
+
+/// Formats `text` with a GGUF model on the CPU; `config.gpt_model` is the model file path.
+///
+/// Generation runs on a blocking thread and sends each piece back over a channel,
+/// so dropping the returned future (cancel) also stops generation.
+pub async fn format_text(text: String, config: AIConfig, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let model_path = config.gpt_model.clone();
+    let context_size = config.local_context_size.unwrap_or(DEFAULT_CONTEXT_SIZE);
+    let threads = config.local_threads.unwrap_or_else(default_threads);
+    let system_prompt = config.system_prompt;
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+
+    let worker = tokio::task::spawn_blocking(move || generate(&model_path, context_size, threads, &system_prompt, &text, sender));
+
+    let mut on_delta = on_delta;
+    let mut formatted_text = String::new();
+    while let Some(piece) = receiver.recv().await {
+        formatted_text.push_str(&piece);
+        if let Some(on_delta) = on_delta.as_mut() {
+            on_delta(&piece);
+        }
+    }
+
+    worker
+        .await
+        .map_err(|e| AppError::new(ErrorKind::LocalModel, format!("LLM task failed: {}", e)))??;
+
+    Ok(formatted_text.trim().to_string())
+}
+
+fn generate(model_path: &str, context_size: u32, threads: u32, system_prompt: &str, text: &str, sender: mpsc::UnboundedSender<String>) -> Result<(), AppError> {
+    let backend = backend()?;
+    let model = load_model(backend, model_path)?;
+
+    let context_params = LlamaContextParams::default()
+        .with_n_ctx(NonZeroU32::new(context_size))
+        .with_n_threads(threads as i32)
+        .with_n_threads_batch(threads as i32);
+    let mut context = model.new_context(backend, context_params).map_err(llm_error)?;
+
+    // 使用模型自带的对话模板，和云端服务一样拆成 system/user 两条消息
+    let template = model.chat_template(None).map_err(llm_error)?;
+    let messages = vec![
+        LlamaChatMessage::new("system".to_string(), system_prompt.to_string()).map_err(llm_error)?,
+        LlamaChatMessage::new("user".to_string(), text.to_string()).map_err(llm_error)?,
+    ];
+    let prompt = model.apply_chat_template(&template, &messages, true).map_err(llm_error)?;
+    let tokens = model.str_to_token(&prompt, AddBos::Always).map_err(llm_error)?;
+
+    let n_ctx = context.n_ctx() as i32;
+    if tokens.len() as i32 >= n_ctx {
+        return Err(AppError::new(
+            ErrorKind::InvalidRequest,
+            format!("Text is too long for the context size ({} tokens > {})", tokens.len(), n_ctx),
+        ));
+    }
+
+    let mut batch = LlamaBatch::new(tokens.len().max(512), 1);
+    let last = tokens.len() as i32 - 1;
+    for (position, token) in (0_i32..).zip(tokens) {
+        batch.add(token, position, &[0], position == last).map_err(llm_error)?;
+    }
+    context.decode(&mut batch).map_err(llm_error)?;
+
+    // 清理任务要求忠实于原文，使用贪心解码
+    let mut sampler = LlamaSampler::greedy();
+    let mut position = batch.n_tokens();
+    let mut pending: Vec<u8> = Vec::new();
+
+    while position < n_ctx {
+        let token = sampler.sample(&context, batch.n_tokens() - 1);
+        sampler.accept(token);
+        if model.is_eog_token(token) {
+            break;
+        }
+
+        // 一个汉字可能被拆成多个 token，凑齐完整的 UTF-8 字符再发送
+        pending.extend(model.token_to_bytes(token, Special::Tokenize).map_err(llm_error)?);
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(valid) => valid.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        if valid > 0 {
+            let piece = String::from_utf8_lossy(&pending[..valid]).to_string();
+            pending.drain(..valid);
+            if sender.send(piece).is_err() {
+                // 接收端已被丢弃，说明请求被取消
+                return Ok(());
+            }
+        }
+
+        batch.clear();
+        batch.add(token, position, &[0], true).map_err(llm_error)?;
+        context.decode(&mut batch).map_err(llm_error)?;
+        position += 1;
+    }
+
+    if !pending.is_empty() {
+        let _ = sender.send(String::from_utf8_lossy(&pending).to_string());
+    }
+    Ok(())
+}
+
+fn backend() -> Result<&'static LlamaBackend, AppError> {
+    if let Some(backend) = BACKEND.get() {
+        return Ok(backend);
+    }
+    let mut backend = LlamaBackend::init().map_err(llm_error)?;
+    backend.void_logs();
+    Ok(BACKEND.get_or_init(|| backend))
+}
+
+fn load_model(backend: &LlamaBackend, model_path: &str) -> Result<Arc<LlamaModel>, AppError> {
+    let mut cached = MODEL.lock().unwrap();
+    if let Some((path, model)) = cached.as_ref() {
+        if path == model_path {
+            return Ok(model.clone());
+        }
+    }
+
+    let model = LlamaModel::load_from_file(backend, model_path, &LlamaModelParams::default())
+        .map_err(|e| AppError::new(ErrorKind::LocalModel, format!("Failed to load model {}: {}", model_path, e)))?;
+    let model = Arc::new(model);
+    *cached = Some((model_path.to_string(), model.clone()));
+    Ok(model)
+}
+
+fn default_threads() -> u32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(4)
+        .min(8)
+}
+
+fn llm_error<E: std::fmt::Display>(e: E) -> AppError {
+    AppError::new(ErrorKind::LocalModel, format!("Local LLM failed: {}", e))
+}
@@ -0,0 +1,75 @@
+use crate::audio::{self, AIConfig};
+use crate::error::{AppError, ErrorKind};
+use std::sync::{Arc, Mutex};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+/// whisper.cpp 需要 16kHz 单声道输入
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+// 加载模型需要数秒，缓存最近一次使用的模型
+static CONTEXT: Mutex<Option<(String, Arc<WhisperContext>)>> = Mutex::new(None);
+
+/// Transcribes on the CPU with whisper.cpp; `config.whisper_model` is the model file path.
+pub async fn transcribe(file_path: String, config: AIConfig) -> Result<String, AppError> {
+    let samples = audio::read_wav_mono(&file_path, WHISPER_SAMPLE_RATE)?;
+    let model_path = config.whisper_model.clone();
+    let threads = config.local_threads.unwrap_or_else(default_threads);
+
+    let text = tokio::task::spawn_blocking(move || run(&model_path, threads, &samples))
+        .await
+        .map_err(|e| AppError::new(ErrorKind::LocalModel, format!("Whisper task failed: {}", e)))??;
+
+    Ok(text)
+}
+
+fn run(model_path: &str, threads: u32, samples: &[f32]) -> Result<String, AppError> {
+    let context = load_context(model_path)?;
+    let mut state = context.create_state().map_err(whisper_error)?;
+
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_n_threads(threads as i32);
+    params.set_language(Some("zh"));
+    params.set_translate(false);
+    // 引导模型输出简体中文和标点
+    params.set_initial_prompt("以下是普通话的句子，使用简体中文。");
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+
+    state.full(params, samples).map_err(whisper_error)?;
+
+    let segments = state.full_n_segments().map_err(whisper_error)?;
+    let mut text = String::new();
+    for i in 0..segments {
+        let segment = state.full_get_segment_text_lossy(i).map_err(whisper_error)?;
+        text.push_str(segment.trim());
+    }
+    Ok(text)
+}
+
+fn load_context(model_path: &str) -> Result<Arc<WhisperContext>, AppError> {
+    let mut cached = CONTEXT.lock().unwrap();
+    if let Some((path, context)) = cached.as_ref() {
+        if path == model_path {
+            return Ok(context.clone());
+        }
+    }
+
+    let context = WhisperContext::new_with_params(model_path, WhisperContextParameters::default())
+        .map_err(|e| AppError::new(ErrorKind::LocalModel, format!("Failed to load model {}: {}", model_path, e)))?;
+    let context = Arc::new(context);
+    *cached = Some((model_path.to_string(), context.clone()));
+    Ok(context)
+}
+
+fn default_threads() -> u32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(4)
+        .min(8)
+}
+
+fn whisper_error(e: whisper_rs::WhisperError) -> AppError {
+    AppError::new(ErrorKind::LocalModel, format!("Whisper inference failed: {}", e))
+}
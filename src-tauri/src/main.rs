@@ -1,29 +1,90 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::sync::Mutex;
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex, RwLock};
 use tauri::{CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, GlobalShortcutManager};
 use tauri_plugin_positioner::{Position, WindowExt};
+use tokio_util::sync::CancellationToken;
 
+use error::{AppError, ErrorKind};
+
+mod assemblyai;
 mod audio;
+mod azure_auth;
+mod cleaner;
+mod compat;
+mod dashscope;
+mod discovery;
+mod error;
+mod http;
+mod itn;
+mod jobs;
+#[cfg(feature = "local-llm")]
+mod local_llm;
+#[cfg(feature = "local-whisper")]
+mod local_whisper;
+mod models;
+mod modes;
+mod ollama;
+mod prompts;
+mod realtime;
+mod replacements;
+mod retry;
+mod stream;
+#[cfg(feature = "local-vosk")]
+mod streaming_asr;
+mod tencent;
+mod typography;
+mod user_config;
 
 #[derive(Default)]
 struct AppState {
     is_recording: Mutex<bool>,
+    // 当前任务的取消令牌；取消后换成新的，之后开始的任务不受影响
+    cancel: Mutex<CancellationToken>,
+    http: RwLock<http::HttpClients>,
+    user_config: RwLock<user_config::UserConfig>,
+    // user_config.replacements 编译后的结果，随配置一起更新
+    replacements: RwLock<replacements::Replacements>,
 }
 
 #[tauri::command]
-async fn start_recording(_app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<(), String> {
+async fn start_recording(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    provider: Option<String>,
+    whisper_model: Option<String>,
+    api_key: Option<String>,
+    base_url: Option<String>,
+    retry: Option<retry::RetryPolicy>,
+    timeout_ms: Option<u64>
+) -> Result<(), AppError> {
     {
         let mut is_recording = state.is_recording.lock().unwrap();
         if *is_recording {
-            return Err("Already recording".into());
+            return Err(AppError::new(ErrorKind::Recording, "Already recording"));
         }
         *is_recording = true;
     } // MutexGuard 在这里被释放
     
+    let defaults = audio::AIConfig::default();
+    let config = audio::AIConfig {
+        provider: provider.unwrap_or_default(),
+        api_key: api_key.unwrap_or_default(),
+        base_url: base_url.unwrap_or_default(),
+        whisper_model: whisper_model.unwrap_or_default(),
+        retry: retry.unwrap_or_default(),
+        timeout_ms: timeout_ms.unwrap_or(defaults.timeout_ms),
+        ..defaults
+    };
+
     // Start audio recording
-    match audio::start_recording().await {
+    let result = match live_transcription_tap(&app_handle, config) {
+        Ok(tap) => audio::start_recording(tap).await,
+        Err(e) => Err(e),
+    };
+    match result {
         Ok(_) => {
             println!("Recording started");
             Ok(())
@@ -34,17 +95,41 @@ async fn start_recording(_app_handle: tauri::AppHandle, state: tauri::State<'_,
                 let mut is_recording = state.is_recording.lock().unwrap();
                 *is_recording = false;
             }
-            Err(format!("Failed to start recording: {}", e))
+            eprintln!("Failed to start recording: {}", e);
+            Err(e)
+        }
+    }
+}
+
+// 流式识别的服务商在录音的同时识别，中间结果通过 transcription-partial 事件推送
+fn live_transcription_tap(
+    app_handle: &tauri::AppHandle,
+    config: audio::AIConfig,
+) -> Result<Option<mpsc::Sender<audio::AudioFrame>>, AppError> {
+    let emit_partial = {
+        let app_handle = app_handle.clone();
+        move |text: &str| {
+            let _ = app_handle.emit_all("transcription-partial", text);
         }
+    };
+
+    match config.provider.as_str() {
+        "openai-realtime" => Ok(Some(realtime::start(config, emit_partial))),
+        #[cfg(feature = "local-vosk")]
+        "vosk" => {
+            let path = models::resolve_model_path(&models_dir(app_handle, "vosk")?, &config.whisper_model)?;
+            Ok(Some(streaming_asr::start(&path.to_string_lossy(), emit_partial)?))
+        }
+        _ => Ok(None),
     }
 }
 
 #[tauri::command]
-async fn stop_recording(_app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<String, String> {
+async fn stop_recording(_app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<String, AppError> {
     {
         let mut is_recording = state.is_recording.lock().unwrap();
         if !*is_recording {
-            return Err("Not recording".into());
+            return Err(AppError::new(ErrorKind::Recording, "Not recording"));
         }
         *is_recording = false;
     } // MutexGuard 在这里被释放
@@ -55,12 +140,17 @@ async fn stop_recording(_app_handle: tauri::AppHandle, state: tauri::State<'_, A
             println!("Recording stopped, saved to: {}", file_path);
             Ok(file_path)
         }
-        Err(e) => Err(format!("Failed to stop recording: {}", e))
+        Err(e) => {
+            eprintln!("Failed to stop recording: {}", e);
+            Err(e)
+        }
     }
 }
 
 #[tauri::command]
 async fn transcribe_audio(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
     file_path: String, 
     provider: String,
     api_key: String,
@@ -69,23 +159,61 @@ async fn transcribe_audio(
     gpt_model: String,
     api_version: Option<String>,
     whisper_deployment: Option<String>,
-    gpt_deployment: Option<String>
-) -> Result<String, String> {
+    gpt_deployment: Option<String>,
+    retry: Option<retry::RetryPolicy>,
+    timeout_ms: Option<u64>,
+    local_threads: Option<u32>,
+    api_secret: Option<String>,
+    azure_auth: Option<azure_auth::AzureAuth>,
+    itn: Option<bool>
+) -> Result<String, AppError> {
+    let defaults = audio::AIConfig::default();
+    let cancel = state.cancel_token();
     let config = audio::AIConfig {
         provider,
         api_key,
+        api_secret,
         base_url,
         whisper_model,
         gpt_model,
         api_version,
         whisper_deployment,
         gpt_deployment,
+        azure_auth,
+        retry: retry.unwrap_or_default(),
+        timeout_ms: timeout_ms.unwrap_or(defaults.timeout_ms),
+        local_threads,
+        local_context_size: None,
+        ollama: ollama::OllamaOptions::default(),
+        custom: None,
+        system_prompt: defaults.system_prompt,
+        temperature: defaults.temperature,
     };
-    audio::transcribe_audio(file_path, config).await
+    let local_model_kind = match config.provider.as_str() {
+        "whisper-local" => Some("whisper"),
+        "vosk" => Some("vosk"),
+        _ => None,
+    };
+    let config = if let Some(kind) = local_model_kind {
+        // 本地模型填写的是模型名称，转换成模型目录下的完整路径
+        let path = models::resolve_model_path(&models_dir(&app_handle, kind)?, &config.whisper_model)?;
+        audio::AIConfig { whisper_model: path.to_string_lossy().to_string(), ..config }
+    } else {
+        config
+    };
+    let config = with_custom_provider(&state, config);
+    let client = state.http.read().unwrap().for_provider(&config.provider);
+    let text = run_cancellable(&cancel, audio::transcribe_audio(&client, file_path.clone(), config)).await?;
+    remove_recording(&file_path);
+    // 数字规范化在替换词典之前，替换规则可以针对规范化后的写法
+    let text = if itn.unwrap_or(false) { itn::normalize(&text) } else { text };
+    Ok(state.replacements.read().unwrap().apply(&text))
 }
 
 #[tauri::command]
 async fn format_text(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
     text: String, 
     provider: String,
     api_key: String,
@@ -94,23 +222,367 @@ async fn format_text(
     gpt_model: String,
     api_version: Option<String>,
     whisper_deployment: Option<String>,
-    gpt_deployment: Option<String>
-) -> Result<String, String> {
+    gpt_deployment: Option<String>,
+    retry: Option<retry::RetryPolicy>,
+    timeout_ms: Option<u64>,
+    stream: Option<bool>,
+    ollama: Option<ollama::OllamaOptions>,
+    local_threads: Option<u32>,
+    local_context_size: Option<u32>,
+    azure_auth: Option<azure_auth::AzureAuth>,
+    prompt: Option<prompts::PromptOptions>,
+    typography: Option<typography::TypographyOptions>
+) -> Result<String, AppError> {
+    let defaults = audio::AIConfig::default();
+    let cancel = state.cancel_token();
+    let prompt = prompt.unwrap_or_default();
+    let user_config = state.user_config.read().unwrap().clone();
+    let system_prompt = prompts::system_prompt(&user_config, &prompt, Some(&text)).await?;
     let config = audio::AIConfig {
         provider,
         api_key,
+        api_secret: None,
         base_url,
         whisper_model,
         gpt_model,
         api_version,
         whisper_deployment,
         gpt_deployment,
+        azure_auth,
+        retry: retry.unwrap_or_default(),
+        timeout_ms: timeout_ms.unwrap_or(defaults.timeout_ms),
+        local_threads,
+        local_context_size,
+        ollama: ollama.unwrap_or_default(),
+        custom: None,
+        system_prompt,
+        temperature: prompt.mode.temperature(),
     };
-    audio::format_text(text, config).await
+    let config = if config.provider == "llama-local" {
+        // 本地模型填写的是模型文件名，转换成模型目录下的完整路径
+        let path = models::resolve_model_path(&models_dir(&app_handle, "llm")?, &config.gpt_model)?;
+        audio::AIConfig { gpt_model: path.to_string_lossy().to_string(), ..config }
+    } else {
+        config
+    };
+    let config = with_custom_provider(&state, config);
+    let client = state.http.read().unwrap().for_provider(&config.provider);
+
+    // 流式输出时，每个增量片段通过 format-text-delta 事件推送给前端
+    let mut emit_delta = |delta: &str| {
+        let _ = app_handle.emit_all("format-text-delta", delta);
+    };
+    let on_delta: Option<stream::DeltaSink<'_>> = if stream.unwrap_or(false) {
+        Some(&mut emit_delta)
+    } else {
+        None
+    };
+    let result = run_cancellable(&cancel, modes::format_text(&client, text.clone(), config, prompt.mode, prompt.bilingual, on_delta)).await;
+    let text = match result {
+        // 清理模式下文本服务出错时改用本地规则清理，并通知前端
+        Err(e) if prompt.mode == modes::OutputMode::Clean && e.kind != ErrorKind::Cancelled => {
+            eprintln!("Text provider failed, falling back to rule-based cleanup: {}", e);
+            let _ = app_handle.emit_all("format-text-fallback", &e);
+            cleaner::clean(&text)
+        }
+        result => result?,
+    };
+    Ok(finish_text(&state, &text, typography.unwrap_or_default()))
 }
 
+/// Single-call mode: sends the recording straight to an audio-capable chat model
+/// and returns the formatted text, skipping `transcribe_audio`.
 #[tauri::command]
-async fn copy_to_clipboard(text: String) -> Result<(), String> {
+async fn process_audio(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    file_path: String,
+    provider: String,
+    api_key: String,
+    base_url: String,
+    gpt_model: String,
+    retry: Option<retry::RetryPolicy>,
+    timeout_ms: Option<u64>,
+    stream: Option<bool>,
+    prompt: Option<prompts::PromptOptions>,
+    typography: Option<typography::TypographyOptions>
+) -> Result<String, AppError> {
+    let defaults = audio::AIConfig::default();
+    let cancel = state.cancel_token();
+    let prompt = prompt.unwrap_or_default();
+    let user_config = state.user_config.read().unwrap().clone();
+    let system_prompt = prompts::system_prompt(&user_config, &prompt, None).await?;
+    let config = audio::AIConfig {
+        provider,
+        api_key,
+        base_url,
+        gpt_model,
+        retry: retry.unwrap_or_default(),
+        timeout_ms: timeout_ms.unwrap_or(defaults.timeout_ms),
+        system_prompt,
+        temperature: prompt.mode.temperature(),
+        ..defaults
+    };
+    let config = with_custom_provider(&state, config);
+    let client = state.http.read().unwrap().for_provider(&config.provider);
+
+    let mut emit_delta = |delta: &str| {
+        let _ = app_handle.emit_all("format-text-delta", delta);
+    };
+    let on_delta: Option<stream::DeltaSink<'_>> = if stream.unwrap_or(false) {
+        Some(&mut emit_delta)
+    } else {
+        None
+    };
+    let text = run_cancellable(&cancel, audio::process_audio(&client, file_path.clone(), config, on_delta)).await?;
+    remove_recording(&file_path);
+    let text = prompt.mode.post_process(&text);
+    Ok(finish_text(&state, &text, typography.unwrap_or_default()))
+}
+
+// 格式化结果最后应用替换词典，再做中英文排版规范化
+fn finish_text(state: &AppState, text: &str, typography: typography::TypographyOptions) -> String {
+    let text = state.replacements.read().unwrap().apply(text);
+    typography::apply(&text, &typography)
+}
+
+// 到用户配置里查找同 id 的服务商，包括覆盖内置服务商的条目
+fn with_custom_provider(state: &AppState, config: audio::AIConfig) -> audio::AIConfig {
+    let custom = state.user_config.read().unwrap().custom_provider(&config.provider).cloned();
+    audio::AIConfig { custom, ..config }
+}
+
+fn user_config_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    app_handle
+        .path_resolver()
+        .app_config_dir()
+        .map(|dir| dir.join("config.json"))
+        .ok_or_else(|| AppError::new(ErrorKind::Config, "App config directory is unavailable"))
+}
+
+// 替换规则编译失败时保留原来的配置
+fn set_user_config(state: &AppState, config: user_config::UserConfig) -> Result<(), AppError> {
+    let replacements = replacements::Replacements::new(&config.replacements)?;
+    *state.replacements.write().unwrap() = replacements;
+    *state.user_config.write().unwrap() = config;
+    Ok(())
+}
+
+/// Re-reads config.json so edits take effect without restarting.
+#[tauri::command]
+fn reload_user_config(app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<user_config::UserConfig, AppError> {
+    let config = user_config::UserConfig::load(&user_config_path(&app_handle)?)?;
+    set_user_config(&state, config.clone())?;
+    Ok(config)
+}
+
+/// Merges a replacement dictionary file into config.json; returns the number of rules afterwards.
+#[tauri::command]
+fn import_replacements(app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>, path: String) -> Result<usize, AppError> {
+    let imported = replacements::import(std::path::Path::new(&path))?;
+    // 从磁盘重新读取，避免覆盖用户手动修改但还没重新加载的内容
+    let config_path = user_config_path(&app_handle)?;
+    let mut config = user_config::UserConfig::load(&config_path)?;
+    replacements::merge(&mut config.replacements, imported);
+    config.save_replacements(&config_path)?;
+    let count = config.replacements.len();
+    set_user_config(&state, config)?;
+    Ok(count)
+}
+
+#[tauri::command]
+fn export_replacements(state: tauri::State<'_, AppState>, path: String) -> Result<(), AppError> {
+    let rules = state.user_config.read().unwrap().replacements.clone();
+    replacements::export(std::path::Path::new(&path), &rules)
+}
+
+/// Built-in and user prompt templates, for the template picker.
+#[tauri::command]
+fn list_prompt_templates(state: tauri::State<'_, AppState>) -> Vec<prompts::PromptTemplate> {
+    prompts::list_templates(&state.user_config.read().unwrap())
+}
+
+#[tauri::command]
+fn set_network_config(state: tauri::State<'_, AppState>, config: http::NetworkConfig) -> Result<(), AppError> {
+    // 先构建新的客户端，配置无效时保留旧的
+    let clients = http::HttpClients::new(config)?;
+    *state.http.write().unwrap() = clients;
+    Ok(())
+}
+
+#[tauri::command]
+fn get_network_config(state: tauri::State<'_, AppState>) -> http::NetworkConfig {
+    state.http.read().unwrap().config().clone()
+}
+
+#[tauri::command]
+async fn cancel_processing(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    std::mem::take(&mut *state.cancel.lock().unwrap()).cancel();
+    Ok(())
+}
+
+impl AppState {
+    // 命令开始时取出令牌，请求发出之前就取消也不会丢失
+    fn cancel_token(&self) -> CancellationToken {
+        self.cancel.lock().unwrap().clone()
+    }
+}
+
+// 只在识别成功后删除录音，出错或取消时保留文件以便重试
+fn remove_recording(file_path: &str) {
+    let _ = std::fs::remove_file(file_path);
+}
+
+// Dropping the request future aborts the in-flight HTTP request.
+async fn run_cancellable<F>(cancel: &CancellationToken, task: F) -> Result<String, AppError>
+where
+    F: std::future::Future<Output = Result<String, AppError>>,
+{
+    tokio::select! {
+        result = task => result,
+        _ = cancel.cancelled() => {
+            println!("Processing cancelled");
+            Err(AppError::cancelled())
+        }
+    }
+}
+
+fn models_dir(app_handle: &tauri::AppHandle, kind: &str) -> Result<PathBuf, AppError> {
+    app_handle
+        .path_resolver()
+        .app_data_dir()
+        .map(|dir| dir.join("models").join(kind))
+        .ok_or_else(|| AppError::new(ErrorKind::LocalModel, "App data directory is unavailable"))
+}
+
+#[tauri::command]
+fn list_whisper_models(app_handle: tauri::AppHandle) -> Result<Vec<models::ModelInfo>, AppError> {
+    models::list_models(&models_dir(&app_handle, "whisper")?, "bin")
+}
+
+#[tauri::command]
+async fn download_whisper_model(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    name: String,
+    mirror: Option<String>
+) -> Result<models::ModelInfo, AppError> {
+    if !models::WHISPER_MODELS.contains(&name.as_str()) {
+        return Err(AppError::new(ErrorKind::LocalModel, format!("Unknown whisper model: {}", name)));
+    }
+
+    let url = models::whisper_model_url(&name, mirror.as_deref());
+    let client = state.http.read().unwrap().for_provider("whisper-local");
+    let dir = models_dir(&app_handle, "whisper")?;
+    let file_name = format!("ggml-{}.bin", name);
+    models::download_model(&client, &url, &dir, &file_name, |progress| {
+        let _ = app_handle.emit_all("model-download-progress", progress);
+    })
+    .await
+}
+
+#[tauri::command]
+fn import_whisper_model(app_handle: tauri::AppHandle, path: String) -> Result<models::ModelInfo, AppError> {
+    models::import_model(std::path::Path::new(&path), &models_dir(&app_handle, "whisper")?)
+}
+
+#[tauri::command]
+fn list_llm_models(app_handle: tauri::AppHandle) -> Result<Vec<models::ModelInfo>, AppError> {
+    models::list_models(&models_dir(&app_handle, "llm")?, "gguf")
+}
+
+#[tauri::command]
+fn import_llm_model(app_handle: tauri::AppHandle, path: String) -> Result<models::ModelInfo, AppError> {
+    models::import_model(std::path::Path::new(&path), &models_dir(&app_handle, "llm")?)
+}
+
+#[tauri::command]
+async fn list_ollama_models(state: tauri::State<'_, AppState>, base_url: String) -> Result<Vec<ollama::OllamaModel>, AppError> {
+    let client = state.http.read().unwrap().for_provider("ollama");
+    ollama::list_models(&client, &base_url).await
+}
+
+#[tauri::command]
+async fn pull_ollama_model(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    base_url: String,
+    model: String
+) -> Result<(), AppError> {
+    let client = state.http.read().unwrap().for_provider("ollama");
+    ollama::pull_model(&client, &base_url, &model, |progress| {
+        let _ = app_handle.emit_all("ollama-pull-progress", progress);
+    })
+    .await
+}
+
+/// Lists the models the provider offers for the given key, or the files in the local model directory.
+#[tauri::command]
+async fn list_models(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    provider: String,
+    api_key: String,
+    base_url: String,
+    api_version: Option<String>,
+    azure_auth: Option<azure_auth::AzureAuth>
+) -> Result<Vec<String>, AppError> {
+    // 本地模型列出模型目录中的文件
+    let local = match provider.as_str() {
+        "whisper-local" => Some(("whisper", "bin")),
+        "llama-local" => Some(("llm", "gguf")),
+        _ => None,
+    };
+    if let Some((kind, extension)) = local {
+        let models = models::list_models(&models_dir(&app_handle, kind)?, extension)?;
+        return Ok(models.into_iter().map(|model| model.name).collect());
+    }
+
+    let config = connection_config(&state, provider, api_key, None, base_url, api_version, azure_auth);
+    let client = state.http.read().unwrap().for_provider(&config.provider);
+    discovery::list_models(&client, &config).await
+}
+
+/// Checks the key, base URL and model without spending tokens and returns a diagnosis.
+#[tauri::command]
+async fn test_connection(
+    state: tauri::State<'_, AppState>,
+    provider: String,
+    api_key: String,
+    api_secret: Option<String>,
+    base_url: String,
+    api_version: Option<String>,
+    model: String,
+    azure_auth: Option<azure_auth::AzureAuth>
+) -> Result<discovery::ConnectionReport, AppError> {
+    let config = connection_config(&state, provider, api_key, api_secret, base_url, api_version, azure_auth);
+    let client = state.http.read().unwrap().for_provider(&config.provider);
+    Ok(discovery::test_connection(&client, &config, &model).await)
+}
+
+fn connection_config(
+    state: &AppState,
+    provider: String,
+    api_key: String,
+    api_secret: Option<String>,
+    base_url: String,
+    api_version: Option<String>,
+    azure_auth: Option<azure_auth::AzureAuth>,
+) -> audio::AIConfig {
+    let config = audio::AIConfig {
+        provider,
+        api_key,
+        api_secret,
+        base_url,
+        api_version,
+        azure_auth,
+        ..Default::default()
+    };
+    with_custom_provider(state, config)
+}
+
+#[tauri::command]
+async fn copy_to_clipboard(text: String) -> Result<(), AppError> {
     audio::copy_to_clipboard(text).await
 }
 
@@ -197,6 +669,18 @@ fn main() {
             _ => {}
         })
         .setup(|app| {
+            // 配置文件有误时使用默认配置启动，前端调用 reload_user_config 时会看到错误
+            if let Ok(path) = user_config_path(&app.handle()) {
+                match user_config::UserConfig::load(&path) {
+                    Ok(config) => {
+                        if let Err(e) = set_user_config(&app.state::<AppState>(), config) {
+                            eprintln!("Failed to load user config: {}", e);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to load user config: {}", e),
+                }
+            }
+
             // Register global shortcut Ctrl+Alt+R
             let app_handle = app.handle();
             
@@ -214,6 +698,23 @@ fn main() {
             stop_recording,
             transcribe_audio,
             format_text,
+            process_audio,
+            cancel_processing,
+            set_network_config,
+            get_network_config,
+            reload_user_config,
+            import_replacements,
+            export_replacements,
+            list_prompt_templates,
+            list_whisper_models,
+            download_whisper_model,
+            import_whisper_model,
+            list_llm_models,
+            import_llm_model,
+            list_ollama_models,
+            pull_ollama_model,
+            list_models,
+            test_connection,
             copy_to_clipboard,
             set_global_shortcut
         ])
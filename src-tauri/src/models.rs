@@ -0,0 +1,146 @@
+use crate::error::{AppError, ErrorKind};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// whisper.cpp 官方 GGML 模型，文件名为 ggml-{name}.bin
+pub const WHISPER_MODELS: &[&str] = &[
+    "tiny", "tiny.en", "base", "base.en", "small", "small.en",
+    "medium", "medium.en", "large-v3", "large-v3-turbo",
+];
+
+/// Download URL of an official whisper.cpp model, optionally through a Hugging Face mirror.
+pub fn whisper_model_url(name: &str, mirror: Option<&str>) -> String {
+    let host = mirror.unwrap_or("https://huggingface.co").trim_end_matches('/');
+    format!("{}/ggerganov/whisper.cpp/resolve/main/ggml-{}.bin", host, name)
+}
+
+/// 本地模型目录中的一个模型文件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+}
+
+/// Progress of a model download, emitted as the `model-download-progress` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub name: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+pub fn list_models(dir: &Path, extension: &str) -> Result<Vec<ModelInfo>, AppError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = std::fs::read_dir(dir).map_err(model_error("Failed to read model directory"))?;
+    let mut models = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(extension) {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        models.push(ModelInfo {
+            name: entry.file_name().to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
+            size,
+        });
+    }
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(models)
+}
+
+/// Resolves a model name from the settings to a file in `dir`, unless it is already a path.
+pub fn resolve_model_path(dir: &Path, name: &str) -> Result<PathBuf, AppError> {
+    let path = Path::new(name);
+    let path = if path.is_absolute() { path.to_path_buf() } else { dir.join(name) };
+
+    if !path.exists() {
+        return Err(AppError::new(ErrorKind::LocalModel, format!("Model not found: {}", path.display())));
+    }
+    Ok(path)
+}
+
+/// Copies a model file the user already has into the model directory.
+pub fn import_model(source: &Path, dir: &Path) -> Result<ModelInfo, AppError> {
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| AppError::new(ErrorKind::LocalModel, format!("Invalid model path: {}", source.display())))?;
+
+    std::fs::create_dir_all(dir).map_err(model_error("Failed to create model directory"))?;
+    let dest = dir.join(file_name);
+    let size = std::fs::copy(source, &dest).map_err(model_error("Failed to import model"))?;
+
+    Ok(ModelInfo {
+        name: file_name.to_string_lossy().to_string(),
+        path: dest.to_string_lossy().to_string(),
+        size,
+    })
+}
+
+/// Downloads `url` into `dir/name`, reporting progress after every chunk.
+///
+/// The file is written to a `.part` file first and renamed when complete, so an
+/// interrupted download never leaves a truncated model behind.
+pub async fn download_model<F>(
+    client: &reqwest::Client,
+    url: &str,
+    dir: &Path,
+    name: &str,
+    mut on_progress: F,
+) -> Result<ModelInfo, AppError>
+where
+    F: FnMut(DownloadProgress),
+{
+    std::fs::create_dir_all(dir).map_err(model_error("Failed to create model directory"))?;
+    let dest = dir.join(name);
+    let partial = dir.join(format!("{}.part", name));
+
+    let mut response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let total = response.content_length();
+    let mut file = tokio::fs::File::create(&partial)
+        .await
+        .map_err(model_error("Failed to create model file"))?;
+
+    let mut downloaded = 0u64;
+    loop {
+        // 大文件下载不设总超时，只在连接停滞时放弃
+        let chunk = tokio::time::timeout(Duration::from_secs(60), response.chunk())
+            .await
+            .map_err(|_| AppError::new(ErrorKind::Timeout, "Model download stalled"))??;
+        let chunk = match chunk {
+            Some(chunk) => chunk,
+            None => break,
+        };
+        file.write_all(&chunk).await.map_err(model_error("Failed to write model file"))?;
+        downloaded += chunk.len() as u64;
+        on_progress(DownloadProgress { name: name.to_string(), downloaded, total });
+    }
+    file.flush().await.map_err(model_error("Failed to write model file"))?;
+    drop(file);
+
+    tokio::fs::rename(&partial, &dest)
+        .await
+        .map_err(model_error("Failed to save model file"))?;
+
+    Ok(ModelInfo {
+        name: name.to_string(),
+        path: dest.to_string_lossy().to_string(),
+        size: downloaded,
+    })
+}
+
+fn model_error<E: std::fmt::Display>(context: &'static str) -> impl Fn(E) -> AppError {
+    move |e| AppError::new(ErrorKind::LocalModel, format!("{}: {}", context, e))
+}
@@ -0,0 +1,272 @@
+use crate::audio::{self, AIConfig};
+use crate::cleaner;
+use crate::error::AppError;
+use crate::stream::DeltaSink;
+use serde::Deserialize;
+
+/// What `format_text` turns the transcript into, chosen per request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputMode {
+    /// 不调用模型，原样返回转写结果
+    Verbatim,
+    /// 中文清理：去掉语气词和重复，修正语法
+    #[default]
+    Clean,
+    Markdown,
+    /// 正式邮件
+    Email,
+    /// git 提交信息
+    Commit,
+    /// 要点摘要
+    Summary,
+    /// 把口述整理成给 AI 编程助手的结构化提示词
+    Prompt,
+    /// 翻译成目标语言
+    Translate,
+}
+
+/// Kind of dictation in prompt mode; each has its own prompt skeleton.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    Question,
+    Instruction,
+    BugReport,
+}
+
+impl OutputMode {
+    /// 未指定模板时使用的内置模板；提示词模式按转写内容选择骨架，没有转写文本时由模型判断
+    pub fn template(self, text: Option<&str>) -> &'static str {
+        match self {
+            OutputMode::Prompt => match text.map(classify) {
+                Some(PromptKind::Question) => "prompt-question",
+                Some(PromptKind::Instruction) => "prompt-instruction",
+                Some(PromptKind::BugReport) => "prompt-bug",
+                None => "prompt",
+            },
+            OutputMode::Verbatim => "verbatim",
+            OutputMode::Clean => "default",
+            OutputMode::Markdown => "markdown",
+            OutputMode::Email => "email",
+            OutputMode::Commit => "commit",
+            OutputMode::Summary => "summary",
+            OutputMode::Translate => "translate",
+        }
+    }
+
+    pub fn temperature(self) -> f32 {
+        match self {
+            OutputMode::Verbatim => 0.0,
+            OutputMode::Commit => 0.2,
+            OutputMode::Email => 0.5,
+            OutputMode::Clean | OutputMode::Markdown | OutputMode::Summary | OutputMode::Prompt | OutputMode::Translate => 0.3,
+        }
+    }
+
+    /// Fixes up what models commonly get wrong for this mode.
+    pub fn post_process(self, text: &str) -> String {
+        let text = strip_code_fence(text.trim());
+        match self {
+            OutputMode::Verbatim | OutputMode::Markdown | OutputMode::Prompt => text.to_string(),
+            OutputMode::Clean | OutputMode::Email | OutputMode::Translate => strip_wrapping_quotes(text).to_string(),
+            OutputMode::Commit => commit_message(text),
+            OutputMode::Summary => bullet_list(text),
+        }
+    }
+}
+
+/// Formats `text` for `mode`; `config.system_prompt` must already be the mode's prompt.
+///
+/// Verbatim skips the model entirely, and so does clean mode when `NO_UPLOAD` is set
+/// and the provider is not local. Streamed deltas are the raw model output,
+/// the returned text is post-processed. With `bilingual`, translate mode returns
+/// the original followed by the translation.
+pub async fn format_text(
+    client: &reqwest::Client,
+    text: String,
+    config: AIConfig,
+    mode: OutputMode,
+    bilingual: bool,
+    on_delta: Option<DeltaSink<'_>>,
+) -> Result<String, AppError> {
+    if mode == OutputMode::Verbatim {
+        return Ok(mode.post_process(&text));
+    }
+    if mode == OutputMode::Clean && cleaner::uploads_disabled() && !runs_locally(&config) {
+        return Ok(cleaner::clean(&text));
+    }
+    let original = (mode == OutputMode::Translate && bilingual).then(|| text.trim().to_string());
+    let formatted_text = mode.post_process(&audio::format_text(client, text, config, on_delta).await?);
+    match original {
+        Some(original) => Ok(format!("{}\n\n{}", original, formatted_text)),
+        None => Ok(formatted_text),
+    }
+}
+
+// 本地模型和本机上的服务（Ollama 等）不算上传
+fn runs_locally(config: &AIConfig) -> bool {
+    config.provider == "llama-local"
+        || reqwest::Url::parse(&config.base_url)
+            .is_ok_and(|url| matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")))
+}
+
+// 报错、崩溃这类词说明是在描述问题，即使是问句（"为什么会报错"）也按问题报告处理
+// 单独的 "错误" 太宽泛（"修正语法错误" 是指令），只认带上下文的说法
+const BUG_KEYWORDS: &[&str] = &[
+    "报错", "出错了", "错误信息", "错误码", "错误提示", "异常", "崩溃", "闪退", "卡死", "白屏", "不生效", "不工作",
+    "没反应", "复现", "堆栈",
+];
+
+// 英文按完整单词匹配，"terror" 不算 error
+const BUG_WORDS: &[&str] = &[
+    "bug", "bugs", "error", "errors", "exception", "exceptions", "crash", "crashes", "crashed", "panic", "panicked",
+    "traceback", "stack trace", "failed", "failing", "broken",
+];
+
+// 中文疑问词常在句中（"这个函数为什么慢"），英文疑问词在句首
+const QUESTION_WORDS: &[&str] = &[
+    "为什么", "怎么", "是什么", "能不能", "可不可以", "有没有", "哪个", "哪些", "请问",
+];
+
+// "如何"、"是否" 常出现在指令中间（"检查是否为空"），只在句首算提问
+const QUESTION_STARTS: &[&str] = &[
+    "如何", "是否", "why", "how", "what", "which", "is ", "are ", "can ", "could ", "should ", "does ",
+];
+
+// "么" 结尾只认 "什么"、"怎么"，"这么"、"那么" 结尾的是指令
+const QUESTION_ENDS: &[&str] = &["?", "？", "吗", "呢", "什么", "怎么"];
+
+/// Guesses whether the dictation is a question, an instruction or a bug report.
+pub fn classify(text: &str) -> PromptKind {
+    let lowered = text.trim().to_lowercase();
+
+    if BUG_KEYWORDS.iter().any(|keyword| lowered.contains(keyword)) || has_bug_word(text) {
+        return PromptKind::BugReport;
+    }
+
+    // 口述常带结尾的语气词和标点，去掉后再判断
+    let body = lowered.trim_end_matches(|c: char| c.is_whitespace() || "。.！!，,啊呀".contains(c));
+    if QUESTION_WORDS.iter().any(|word| body.contains(word))
+        || QUESTION_STARTS.iter().any(|start| body.starts_with(start))
+        || QUESTION_ENDS.iter().any(|end| body.ends_with(end))
+    {
+        return PromptKind::Question;
+    }
+    PromptKind::Instruction
+}
+
+fn has_bug_word(text: &str) -> bool {
+    let tokens: Vec<&str> = text.split(|c: char| !c.is_ascii_alphanumeric()).filter(|t| !t.is_empty()).collect();
+    // 异常类名（TypeError、NullPointerException）也算
+    if tokens.iter().any(|t| t.len() > 5 && (t.ends_with("Error") || t.ends_with("Exception"))) {
+        return true;
+    }
+    let words = format!(" {} ", tokens.join(" ").to_lowercase());
+    BUG_WORDS.iter().any(|word| words.contains(&format!(" {} ", word)))
+}
+
+// 模型经常把整段输出包在 ``` 代码块里
+fn strip_code_fence(text: &str) -> &str {
+    let Some(inner) = text.strip_prefix("```").and_then(|rest| rest.strip_suffix("```")) else {
+        return text;
+    };
+    // 去掉开头的语言标记，如 ```markdown
+    match inner.split_once('\n') {
+        Some((lang, body)) if !lang.contains(' ') => body.trim(),
+        _ => inner.trim(),
+    }
+}
+
+fn strip_wrapping_quotes(text: &str) -> &str {
+    for (open, close) in [("\"", "\""), ("“", "”"), ("「", "」")] {
+        if let Some(inner) = text.strip_prefix(open).and_then(|rest| rest.strip_suffix(close)) {
+            if !inner.contains(open) && !inner.contains(close) {
+                return inner.trim();
+            }
+        }
+    }
+    text
+}
+
+// 标题行不超过 72 个字符、不以句号结尾，正文和标题之间空一行
+fn commit_message(text: &str) -> String {
+    let mut lines = text.lines().map(str::trim_end);
+    let subject = lines.next().unwrap_or_default();
+    let subject = subject.trim_end_matches(['.', '。']);
+    let subject: String = if subject.chars().count() > 72 { subject.chars().take(72).collect() } else { subject.to_string() };
+
+    let body: Vec<&str> = lines.skip_while(|line| line.is_empty()).collect();
+    if body.is_empty() {
+        subject
+    } else {
+        format!("{}\n\n{}", subject, body.join("\n"))
+    }
+}
+
+// 统一使用 "- " 作为列表符号
+fn bullet_list(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let indent = &line[..line.len() - trimmed.len()];
+            let item = ["* ", "• ", "· ", "+ "]
+                .iter()
+                .find_map(|marker| trimmed.strip_prefix(marker))
+                .or_else(|| strip_ordinal(trimmed));
+            match item {
+                Some(item) => format!("{}- {}", indent, item.trim_start()),
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// "1. "、"2) "、"3、" 这样的编号
+fn strip_ordinal(line: &str) -> Option<&str> {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let rest = &line[digits..];
+    [". ", ") ", "、", "．"].iter().find_map(|marker| rest.strip_prefix(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_dictation() {
+        let cases = [
+            ("这个函数为什么这么慢", PromptKind::Question),
+            ("怎么把列表倒序", PromptKind::Question),
+            ("如何把列表倒序", PromptKind::Question),
+            ("是否需要加锁", PromptKind::Question),
+            ("这里需要检查是否为空吗", PromptKind::Question),
+            ("这段代码有问题吗", PromptKind::Question),
+            ("这个参数到底是干什么", PromptKind::Question),
+            ("how do I reverse a list in Python", PromptKind::Question),
+            ("is this thread safe?", PromptKind::Question),
+            ("帮我写一个排序函数", PromptKind::Instruction),
+            ("把这个函数改成异步的。", PromptKind::Instruction),
+            ("把这一段改成这么", PromptKind::Instruction),
+            ("缩进不用那么", PromptKind::Instruction),
+            ("修正语法错误", PromptKind::Instruction),
+            ("检查是否为空", PromptKind::Instruction),
+            ("说明如何部署的文档写一下", PromptKind::Instruction),
+            ("写一篇关于 terror 的短文", PromptKind::Instruction),
+            ("把 errorHandler 重命名为 onFailure", PromptKind::Instruction),
+            ("rename the variable to total", PromptKind::Instruction),
+            ("点保存之后应用闪退", PromptKind::BugReport),
+            ("为什么运行的时候会报错", PromptKind::BugReport),
+            ("日志里的错误信息是空的", PromptKind::BugReport),
+            ("TypeError: undefined is not a function", PromptKind::BugReport),
+            ("the app crashes on startup", PromptKind::BugReport),
+            ("build failed 了", PromptKind::BugReport),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(classify(text), expected, "{}", text);
+        }
+    }
+}
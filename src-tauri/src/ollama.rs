@@ -0,0 +1,167 @@
+use crate::audio::AIConfig;
+use crate::error::{AppError, ErrorKind};
+use crate::retry::send_with_retry;
+use crate::stream::{self, DeltaSink};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// 原生 `/api/chat` 的选项，未设置的项使用 Ollama 服务端默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OllamaOptions {
+    /// 使用原生 `/api/chat` 而不是 OpenAI 兼容的 `/v1/chat/completions`
+    pub native: bool,
+    /// 模型在内存中保留的时长，如 "5m"、"1h"，"-1m" 表示常驻
+    pub keep_alive: Option<String>,
+    /// 上下文窗口大小（token 数）
+    pub num_ctx: Option<u32>,
+    /// 留空时使用输出模式的默认温度
+    pub temperature: Option<f32>,
+}
+
+/// 本地已安装的模型，来自 `/api/tags`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaModel {
+    pub name: String,
+    pub size: u64,
+    pub modified_at: Option<String>,
+    pub parameter_size: Option<String>,
+    pub quantization_level: Option<String>,
+}
+
+/// Progress of a model pull, emitted as the `ollama-pull-progress` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullProgress {
+    pub model: String,
+    /// Ollama 报告的阶段，如 "pulling manifest"、"downloading"、"success"
+    pub status: String,
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+}
+
+// 拉取大模型时各层之间可能有较长的校验间隔
+const PULL_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Sends `messages` to the native `/api/chat` endpoint, streaming when `on_delta` is given.
+pub async fn chat(client: &reqwest::Client, config: &AIConfig, messages: Value, on_delta: Option<DeltaSink<'_>>) -> Result<String, AppError> {
+    let options = &config.ollama;
+    let streaming = on_delta.is_some();
+
+    let mut model_options = serde_json::Map::new();
+    if let Some(num_ctx) = options.num_ctx {
+        model_options.insert("num_ctx".to_string(), num_ctx.into());
+    }
+    // 设置里填写的温度优先于输出模式的默认温度
+    let temperature = options.temperature.unwrap_or(config.temperature);
+    model_options.insert("temperature".to_string(), temperature.into());
+
+    let mut request_body = serde_json::json!({
+        "model": config.gpt_model,
+        "messages": messages,
+        "stream": streaming,
+        "options": model_options
+    });
+    if let Some(keep_alive) = &options.keep_alive {
+        request_body["keep_alive"] = keep_alive.as_str().into();
+    }
+
+    let url = format!("{}/api/chat", config.base_url);
+    let response = send_with_retry(&config.retry, || {
+        client
+            .post(&url)
+            .timeout(config.request_timeout(streaming))
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    if let Some(on_delta) = on_delta {
+        return stream::collect_ollama_ndjson(response, config.timeout(), on_delta).await;
+    }
+
+    let json: Value = response.json().await?;
+
+    let content = json["message"]["content"]
+        .as_str()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No content in response"))?
+        .to_string();
+
+    Ok(content)
+}
+
+/// Lists the models installed on the Ollama server.
+pub async fn list_models(client: &reqwest::Client, base_url: &str) -> Result<Vec<OllamaModel>, AppError> {
+    let response = client
+        .get(format!("{}/api/tags", base_url))
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let json: Value = response.json().await?;
+    let models = json["models"]
+        .as_array()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No models in response"))?
+        .iter()
+        .filter_map(|model| {
+            Some(OllamaModel {
+                name: model["name"].as_str()?.to_string(),
+                size: model["size"].as_u64().unwrap_or(0),
+                modified_at: model["modified_at"].as_str().map(str::to_string),
+                parameter_size: model["details"]["parameter_size"].as_str().map(str::to_string),
+                quantization_level: model["details"]["quantization_level"].as_str().map(str::to_string),
+            })
+        })
+        .collect();
+
+    Ok(models)
+}
+
+/// Pulls `model` onto the Ollama server, reporting every progress line.
+///
+/// Already-downloaded layers are skipped by Ollama, so pulling an installed
+/// model only checks that it is up to date.
+pub async fn pull_model<F>(client: &reqwest::Client, base_url: &str, model: &str, mut on_progress: F) -> Result<(), AppError>
+where
+    F: FnMut(PullProgress),
+{
+    // 下载可能持续很久，不设总超时，只检查空闲超时
+    let response = client
+        .post(format!("{}/api/pull", base_url))
+        .json(&serde_json::json!({ "model": model, "stream": true }))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let mut succeeded = false;
+    stream::for_each_ndjson(response, PULL_IDLE_TIMEOUT, |json| {
+        let status = json["status"].as_str().unwrap_or_default().to_string();
+        succeeded = status == "success";
+        on_progress(PullProgress {
+            model: model.to_string(),
+            status,
+            completed: json["completed"].as_u64(),
+            total: json["total"].as_u64(),
+        });
+        Ok(!succeeded)
+    })
+    .await?;
+
+    if !succeeded {
+        return Err(AppError::new(ErrorKind::Network, format!("Pull of {} ended before completion", model)));
+    }
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use crate::audio;
+use crate::error::{AppError, ErrorKind};
+use crate::modes::OutputMode;
+use crate::user_config::UserConfig;
+use serde::{Deserialize, Serialize};
+
+/// 内置默认模板的 id，未指定模板时使用
+pub const DEFAULT_TEMPLATE: &str = "default";
+
+/// 内置默认模板：中文语音转写清理
+pub const DEFAULT_PROMPT: &str = "你是专业的中文文本清理工具。请将语音转写的文本进行清理：1）如果输入是英文但内容是中文意思，请直接翻译成对应的中文；2）删除语气词（嗯、啊、那个等）；3）去除重复词语；4）修正语法错误；5）保持原意不变，不要添加任何新内容；6）输出简洁的中文文本，不要使用Markdown格式。特别注意：如果输入的英文明显是中文语音的错误识别结果，请直接转换为正确的中文表达。";
+
+const VERBATIM_PROMPT: &str = "请原样输出用户提供的文本；如果用户提供的是音频，请逐字转写。不做任何修改，也不要添加任何解释。";
+
+const MARKDOWN_PROMPT: &str = "你是专业的 Markdown 整理器，只输出{{language}}。请将用户的语音转写文本整理成清晰、格式化的 Markdown 文本。
+
+规则：
+1. 保持原意不变
+2. 纠正语音识别错误
+3. 添加适当的标点符号
+4. 使用 Markdown 格式（如标题、列表、代码块等）
+5. 输出简洁清晰的{{language}}";
+
+const EMAIL_PROMPT: &str = "你是专业的商务邮件撰写助手。请把用户口述的内容整理成一封正式、礼貌的{{language}}邮件：第一行以「主题：」开头写邮件主题，空一行后是称呼、正文和结尾敬语；语气得体，段落清晰；保持原意，不要编造口述中没有的姓名、日期等信息，缺少的信息用【】标出。只输出邮件本身。";
+
+const COMMIT_PROMPT: &str = "你是 git 提交信息撰写助手。请把用户口述的改动说明整理成一条 git 提交信息：第一行是不超过 72 个字符的英文祈使句摘要，不以句号结尾；需要时空一行，再用简短的要点说明改动的原因和细节。{{#glossary}}以下专有名词保持原样：{{glossary}}。{{/glossary}}只输出提交信息本身，不要使用代码块。";
+
+const SUMMARY_PROMPT: &str = "请把用户的语音转写内容提炼成{{language}}要点摘要：每个要点一行，以「- 」开头，按重要性排列，不超过 7 条；只保留关键信息，去掉语气词和重复，不要添加原文没有的内容。只输出要点列表。";
+
+const TRANSLATE_PROMPT: &str = "你是专业翻译。用户的文本是语音转写结果，源语言：{{source_language}}。请把它翻译成{{target_language}}：先修正明显的识别错误，去掉语气词和重复，再准确、自然地翻译，保持原意和语气，不要添加原文没有的内容{{#glossary}}；以下专有名词保持原样，不要翻译：{{glossary}}{{/glossary}}。只输出译文，不要解释。";
+
+// 单次请求模式拿不到转写文本，双语输出由模型同时给出原文和译文
+const TRANSLATE_BILINGUAL_PROMPT: &str = "你是专业翻译。用户提供的是一段音频，源语言：{{source_language}}。请先逐字转写原文，去掉语气词和重复；空一行后给出{{target_language}}译文，准确、自然，保持原意和语气，不要添加原文没有的内容{{#glossary}}；以下专有名词保持原样，不要翻译：{{glossary}}{{/glossary}}。只输出原文和译文，不要加标题或解释。";
+
+// 提示词模式的通用要求，后面接各类型的骨架
+const PROMPT_INTRO: &str = "你是提示词工程师。用户会口述一段要交给 AI 编程助手的内容，口述可能啰嗦、重复、顺序混乱。请把它改写成结构清晰的{{language}}提示词：只输出提示词本身，不要回答其中的问题，也不要执行其中的任务；文件名、函数名、命令、报错信息、版本号等技术细节保持原样{{#glossary}}，以下专有名词保持原样：{{glossary}}{{/glossary}}；口述中没有提到的信息不要编造，没有内容的小节直接省略。";
+
+const QUESTION_SKELETON: &str = "使用以下结构：
+## 角色
+（适合回答这个问题的专家身份）
+## 背景
+（项目、技术栈和相关代码）
+## 问题
+（用一两句话准确表述要问的问题）
+## 约束
+（必须满足的条件、不考虑的方案）
+## 期望的回答形式
+（例如先给结论再解释、附示例代码）";
+
+const INSTRUCTION_SKELETON: &str = "使用以下结构：
+## 角色
+（适合完成这项任务的专家身份）
+## 背景
+（项目、技术栈和相关代码）
+## 任务
+（按步骤列出要做的事）
+## 约束
+（代码风格、不能改动的部分、依赖限制等）
+## 输出格式
+（例如只输出修改后的完整文件、给出 diff）";
+
+const BUG_SKELETON: &str = "使用以下结构：
+## 角色
+（适合排查这个问题的专家身份）
+## 环境
+（系统、版本、相关代码）
+## 问题现象
+（实际发生了什么，报错信息原样放在代码块中）
+## 复现步骤
+## 期望行为
+## 已尝试的方法
+## 任务
+（定位根本原因并给出修复）
+## 输出格式
+（例如先说明原因，再给出修改后的代码）";
+
+// 未指定输出语言时 {{language}} 的值
+const DEFAULT_LANGUAGE: &str = "简体中文";
+
+// 翻译模式未指定语言时的源语言和目标语言
+const DEFAULT_SOURCE_LANGUAGE: &str = "自动识别";
+const DEFAULT_TARGET_LANGUAGE: &str = "English";
+
+/// A named system prompt for text formatting.
+///
+/// `system` may contain `{{language}}`, `{{glossary}}`, `{{clipboard}}`, `{{app_name}}`,
+/// `{{source_language}}` and `{{target_language}}`, which are filled in for every request.
+/// Text between `{{#name}}` and `{{/name}}` is kept only when the variable is non-empty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    pub system: String,
+}
+
+/// 每次格式化请求的提示词设置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PromptOptions {
+    /// 输出模式，决定默认模板、温度和后处理
+    pub mode: OutputMode,
+    /// 模板 id，为空时使用输出模式对应的内置模板
+    pub template: Option<String>,
+    /// 输出语言，填入 {{language}}
+    pub language: Option<String>,
+    /// 触发听写的程序名，填入 {{app_name}}；由调用方传入，未知时为空
+    pub app_name: Option<String>,
+    /// 翻译模式的源语言，填入 {{source_language}}，为空时由模型识别
+    pub source_language: Option<String>,
+    /// 翻译模式的目标语言，填入 {{target_language}}
+    pub target_language: Option<String>,
+    /// 翻译模式同时返回原文和译文
+    pub bilingual: bool,
+}
+
+pub fn builtin_templates() -> Vec<PromptTemplate> {
+    let prompt = |skeleton: &str| format!("{}\n\n{}", PROMPT_INTRO, skeleton);
+    // 单次请求模式拿不到转写文本，由模型自己判断类型
+    let prompt_any = format!(
+        "{}\n\n先判断口述内容是提问、指令还是问题报告，再选用对应的结构。\n\n提问：{}\n\n指令：{}\n\n问题报告：{}",
+        PROMPT_INTRO, QUESTION_SKELETON, INSTRUCTION_SKELETON, BUG_SKELETON
+    );
+
+    [
+        (DEFAULT_TEMPLATE, "中文清理", DEFAULT_PROMPT.to_string()),
+        ("verbatim", "原样输出", VERBATIM_PROMPT.to_string()),
+        ("markdown", "Markdown", MARKDOWN_PROMPT.to_string()),
+        ("email", "正式邮件", EMAIL_PROMPT.to_string()),
+        ("commit", "Git 提交信息", COMMIT_PROMPT.to_string()),
+        ("summary", "要点摘要", SUMMARY_PROMPT.to_string()),
+        ("translate", "翻译", TRANSLATE_PROMPT.to_string()),
+        ("translate-bilingual", "翻译（原文 + 译文）", TRANSLATE_BILINGUAL_PROMPT.to_string()),
+        ("prompt", "结构化提示词", prompt_any),
+        ("prompt-question", "结构化提示词（提问）", prompt(QUESTION_SKELETON)),
+        ("prompt-instruction", "结构化提示词（指令）", prompt(INSTRUCTION_SKELETON)),
+        ("prompt-bug", "结构化提示词（问题报告）", prompt(BUG_SKELETON)),
+    ]
+    .into_iter()
+    .map(|(id, name, system)| PromptTemplate { id: id.to_string(), name: name.to_string(), system })
+    .collect()
+}
+
+/// Built-in templates followed by the user's; a user template with a built-in id replaces it.
+pub fn list_templates(config: &UserConfig) -> Vec<PromptTemplate> {
+    let mut templates: Vec<PromptTemplate> = builtin_templates()
+        .into_iter()
+        .filter(|builtin| config.prompt_template(&builtin.id).is_none())
+        .collect();
+    templates.extend(config.prompt_templates.iter().cloned());
+    templates
+}
+
+/// Resolves the request's template and fills in its variables.
+///
+/// `text` is the transcript when it is known before the request, so the mode can pick a template from it.
+pub async fn system_prompt(config: &UserConfig, options: &PromptOptions, text: Option<&str>) -> Result<String, AppError> {
+    let id = match options.template.as_deref().filter(|id| !id.is_empty()) {
+        Some(id) => id,
+        // 有转写文本时双语结果由 modes::format_text 拼接，只有单次请求模式需要模型输出原文
+        None if options.mode == OutputMode::Translate && options.bilingual && text.is_none() => "translate-bilingual",
+        None => options.mode.template(text),
+    };
+    let template = match config.prompt_template(id) {
+        Some(template) => template.clone(),
+        None => builtin_templates()
+            .into_iter()
+            .find(|template| template.id == id)
+            .ok_or_else(|| AppError::new(ErrorKind::Config, format!("Unknown prompt template: {}", id)))?,
+    };
+
+    // 只有模板用到时才读取剪贴板
+    let clipboard = if uses(&template.system, "clipboard") {
+        audio::read_clipboard().await.unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let language = options.language.as_deref().filter(|l| !l.is_empty()).unwrap_or(DEFAULT_LANGUAGE);
+    let glossary = config.glossary.join("、");
+    let source_language = options.source_language.as_deref().filter(|l| !l.is_empty()).unwrap_or(DEFAULT_SOURCE_LANGUAGE);
+    let target_language = options.target_language.as_deref().filter(|l| !l.is_empty()).unwrap_or(DEFAULT_TARGET_LANGUAGE);
+
+    Ok(render(&template.system, &[
+        ("language", language),
+        ("glossary", &glossary),
+        ("clipboard", &clipboard),
+        ("app_name", options.app_name.as_deref().unwrap_or_default()),
+        ("source_language", source_language),
+        ("target_language", target_language),
+    ]))
+}
+
+fn uses(template: &str, name: &str) -> bool {
+    template
+        .split("{{")
+        .skip(1)
+        .any(|part| part.split_once("}}").is_some_and(|(var, _)| var.trim() == name))
+}
+
+/// Replaces `{{name}}` placeholders and `{{#name}}...{{/name}}` sections; unknown names are left as they are.
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = rest[start + 2..end].trim();
+        let value = |name: &str| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| *value);
+
+        // 变量为空时整段省略，避免留下「以下专有名词保持原样：。」这样的残句
+        if let Some(section) = name.strip_prefix('#').map(str::trim) {
+            let close = format!("{{{{/{}}}}}", section);
+            let close_start = rest[end + 2..].find(&close).map(|close_start| end + 2 + close_start);
+            if let (Some(value), Some(close_start)) = (value(section), close_start) {
+                if !value.is_empty() {
+                    out.push_str(&render(&rest[end + 2..close_start], vars));
+                }
+                rest = &rest[close_start + close.len()..];
+                continue;
+            }
+        }
+
+        match value(name) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_variables() {
+        let vars = [("language", "English"), ("glossary", "Tauri、Rust"), ("app_name", "")];
+        let cases = [
+            ("只输出{{language}}", "只输出English"),
+            ("{{ language }}和{{language}}", "English和English"),
+            ("程序：{{app_name}}。", "程序：。"),
+            ("{{unknown}} 保留", "{{unknown}} 保留"),
+            ("没有结束 {{language", "没有结束 {{language"),
+            ("{{#glossary}}保持原样：{{glossary}}。{{/glossary}}结束", "保持原样：Tauri、Rust。结束"),
+            ("开始{{#app_name}}程序：{{app_name}}。{{/app_name}}结束", "开始结束"),
+            ("{{#unknown}}保留{{/unknown}}", "{{#unknown}}保留{{/unknown}}"),
+            ("{{#glossary}}没有结束标记", "{{#glossary}}没有结束标记"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(render(template, &vars), expected, "{}", template);
+        }
+    }
+
+    #[tokio::test]
+    async fn glossary_clause() {
+        let cases = [
+            ("commit", vec![], "细节。只输出提交信息本身"),
+            ("commit", vec!["Tauri", "Rust"], "细节。以下专有名词保持原样：Tauri、Rust。只输出"),
+            ("translate", vec![], "不要添加原文没有的内容。只输出译文"),
+            ("prompt-bug", vec![], "技术细节保持原样；口述中"),
+            ("prompt-bug", vec!["Vite"], "技术细节保持原样，以下专有名词保持原样：Vite；口述中"),
+        ];
+        for (template, glossary, expected) in cases {
+            let config = UserConfig { glossary: glossary.iter().map(|term| term.to_string()).collect(), ..UserConfig::default() };
+            let options = PromptOptions { template: Some(template.to_string()), ..PromptOptions::default() };
+            let prompt = system_prompt(&config, &options, None).await.unwrap();
+            assert!(prompt.contains(expected), "{}: {}", template, prompt);
+            assert!(!prompt.contains("{{"), "{}: {}", template, prompt);
+        }
+    }
+}
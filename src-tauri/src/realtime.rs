@@ -0,0 +1,598 @@
+use crate::audio::{self, AIConfig, AudioFrame};
+use crate::error::{AppError, ErrorKind};
+use crate::retry;
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::sync::{mpsc, Mutex};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Realtime 接口要求 24kHz 单声道 16 位小端 PCM
+const REALTIME_SAMPLE_RATE: u32 = 24_000;
+const BYTES_PER_MS: usize = REALTIME_SAMPLE_RATE as usize / 1000 * 2;
+
+// 服务端拒绝提交不足 100ms 的音频
+const MIN_COMMIT_BYTES: usize = 100 * BYTES_PER_MS;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// 当前录音对应的实时会话，录音结束后由 transcribe 取走结果
+static SESSION: Mutex<Option<JoinHandle<Result<String, AppError>>>> = Mutex::new(None);
+
+/// Starts a realtime transcription session and returns the sender to pass to `audio::start_recording`.
+///
+/// `config.base_url` is the WebSocket endpoint (OpenAI Realtime style, e.g.
+/// `wss://api.openai.com/v1/realtime?intent=transcription`) and
+/// `config.whisper_model` the transcription model. `on_partial` receives the
+/// whole transcript so far every time it changes. The session ends when the
+/// recording stops and the sender is dropped.
+pub fn start<F>(config: AIConfig, mut on_partial: F) -> mpsc::Sender<AudioFrame>
+where
+    F: FnMut(&str) + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<AudioFrame>();
+    let (pcm_sender, pcm_receiver) = unbounded_channel();
+
+    // 录音回调用的是同步通道，在单独的线程里转换格式后转给异步会话
+    std::thread::spawn(move || {
+        while let Ok(frame) = receiver.recv() {
+            let mono = audio::downmix(&frame.samples, frame.channels);
+            let pcm = to_pcm16_le(&audio::resample(&mono, frame.sample_rate, REALTIME_SAMPLE_RATE));
+            if pcm_sender.send(pcm).is_err() {
+                break;
+            }
+        }
+    });
+
+    let session = tokio::spawn(async move { run(&config, pcm_receiver, &mut on_partial).await });
+    if let Some(previous) = SESSION.lock().unwrap().replace(session) {
+        previous.abort();
+    }
+    sender
+}
+
+/// Returns the transcript of the live session if one ran, otherwise streams the file through a new session.
+pub async fn transcribe(file_path: String, config: AIConfig) -> Result<String, AppError> {
+    let session = SESSION.lock().unwrap().take();
+
+    let text = match session {
+        // 取消转写时这个 future 被丢弃，会话也要随之结束
+        Some(session) => AbortOnDrop(session)
+            .wait()
+            .await
+            .map_err(|e| AppError::new(ErrorKind::ServerError, format!("Realtime session failed: {}", e)))??,
+        None => {
+            let samples = audio::read_wav_mono(&file_path, REALTIME_SAMPLE_RATE)?;
+            let (pcm_sender, pcm_receiver) = unbounded_channel();
+            // 按 0.2 秒分块发送，和实时录音时的行为一致
+            for chunk in samples.chunks(REALTIME_SAMPLE_RATE as usize / 5) {
+                let _ = pcm_sender.send(to_pcm16_le(chunk));
+            }
+            drop(pcm_sender);
+            run(&config, pcm_receiver, &mut |_: &str| {}).await?
+        }
+    };
+
+    Ok(text.trim().to_string())
+}
+
+/// Opens and closes a session to check the endpoint and key; no audio is sent.
+pub async fn probe(config: &AIConfig) -> Result<(), AppError> {
+    let mut socket = connect(config).await?;
+    let _ = socket.close(None).await;
+    Ok(())
+}
+
+struct AbortOnDrop(JoinHandle<Result<String, AppError>>);
+
+impl AbortOnDrop {
+    async fn wait(&mut self) -> Result<Result<String, AppError>, tokio::task::JoinError> {
+        (&mut self.0).await
+    }
+}
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+// One transcribed speech segment, committed by the server's voice activity detection
+// or by our final commit when the recording stops.
+struct Segment {
+    item_id: String,
+    text: String,
+    completed: bool,
+    // 这段语音在整个录音中的结束位置（字节）
+    end: usize,
+}
+
+// Everything that has to survive a reconnect.
+#[derive(Default)]
+struct SessionState {
+    segments: Vec<Segment>,
+    // 还没有确认转写完成的音频，从 confirmed 处开始；重连后重新发送
+    unconfirmed: Vec<u8>,
+    confirmed: usize,
+    // 已发送的音频总长度
+    sent: usize,
+    // 当前连接的第一个字节在整个录音中的位置，服务端的毫秒时间从这里算起
+    connection_start: usize,
+    committed_end: usize,
+    speech_end: Option<usize>,
+    audio_done: bool,
+    // 录音结束后发出了提交，还没收到 committed 或空缓冲区的错误
+    awaiting_commit: bool,
+    failure: Option<AppError>,
+}
+
+impl SessionState {
+    fn push_audio(&mut self, pcm: &[u8]) {
+        self.unconfirmed.extend_from_slice(pcm);
+        self.sent += pcm.len();
+    }
+
+    fn segment_mut(&mut self, item_id: &str) -> &mut Segment {
+        let index = match self.segments.iter().position(|s| s.item_id == item_id) {
+            Some(index) => index,
+            None => {
+                let end = self.speech_end.take().unwrap_or(self.sent);
+                self.segments.push(Segment { item_id: item_id.to_string(), text: String::new(), completed: false, end });
+                self.committed_end = self.committed_end.max(end);
+                self.segments.len() - 1
+            }
+        };
+        &mut self.segments[index]
+    }
+
+    fn complete(&mut self, item_id: &str, transcript: &str) {
+        let segment = self.segment_mut(item_id);
+        segment.text = transcript.to_string();
+        segment.completed = true;
+
+        // 前面的语音段都已完成时，对应的音频不再需要重发
+        let done = self.segments.iter().take_while(|s| s.completed).map(|s| s.end).max().unwrap_or(self.confirmed);
+        if done > self.confirmed {
+            self.unconfirmed.drain(..(done - self.confirmed).min(self.unconfirmed.len()));
+            self.confirmed = done;
+        }
+    }
+
+    fn pending(&self) -> bool {
+        self.awaiting_commit || self.segments.iter().any(|s| !s.completed)
+    }
+
+    fn text(&self) -> String {
+        self.segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    // 断线后未完成的语音段随旧连接丢失，从第一个未完成的段开始重新转写
+    fn prepare_reconnect(&mut self) {
+        if let Some(index) = self.segments.iter().position(|s| !s.completed) {
+            self.segments.truncate(index);
+        }
+        self.connection_start = self.confirmed;
+        self.committed_end = self.confirmed;
+        self.speech_end = None;
+        self.awaiting_commit = false;
+    }
+}
+
+async fn run<F>(config: &AIConfig, mut pcm: UnboundedReceiver<Vec<u8>>, on_partial: &mut F) -> Result<String, AppError>
+where
+    F: FnMut(&str) + Send,
+{
+    let mut state = SessionState::default();
+    let mut attempt = 0;
+
+    loop {
+        match stream_session(config, &mut pcm, &mut state, on_partial).await {
+            Ok(()) => break,
+            // 连接断开或握手超时时重连，鉴权、模型名等错误直接返回
+            Err(e) if matches!(e.kind, ErrorKind::Network | ErrorKind::Timeout) && attempt < config.retry.max_retries => {
+                let delay = retry::backoff_delay(&config.retry, attempt);
+                attempt += 1;
+                eprintln!("Realtime session interrupted: {}, reconnect {}/{} in {:?}", e, attempt, config.retry.max_retries, delay);
+                state.prepare_reconnect();
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let text = state.text();
+    match state.failure {
+        Some(failure) if text.trim().is_empty() => Err(failure),
+        _ => Ok(text),
+    }
+}
+
+// Runs one WebSocket connection until the recording has stopped and every segment is transcribed.
+async fn stream_session<F>(
+    config: &AIConfig,
+    pcm: &mut UnboundedReceiver<Vec<u8>>,
+    state: &mut SessionState,
+    on_partial: &mut F,
+) -> Result<(), AppError>
+where
+    F: FnMut(&str) + Send,
+{
+    let mut socket = connect(config).await?;
+
+    let session_update = serde_json::json!({
+        "type": "transcription_session.update",
+        "session": {
+            "input_audio_format": "pcm16",
+            "input_audio_transcription": { "model": config.whisper_model, "language": "zh" },
+            "turn_detection": { "type": "server_vad", "silence_duration_ms": 500 }
+        }
+    });
+    send(&mut socket, &session_update).await?;
+
+    // 重连时先补发尚未确认的音频
+    for chunk in state.unconfirmed.clone().chunks(REALTIME_SAMPLE_RATE as usize) {
+        send(&mut socket, &append_event(chunk)).await?;
+    }
+
+    let mut deadline = None;
+    if state.audio_done {
+        if !finish(&mut socket, state).await? {
+            return Ok(());
+        }
+        deadline = Some(Instant::now() + config.timeout());
+    }
+
+    loop {
+        tokio::select! {
+            chunk = pcm.recv(), if !state.audio_done => match chunk {
+                Some(chunk) => {
+                    send(&mut socket, &append_event(&chunk)).await?;
+                    state.push_audio(&chunk);
+                }
+                None => {
+                    state.audio_done = true;
+                    if !finish(&mut socket, state).await? {
+                        break;
+                    }
+                    deadline = Some(Instant::now() + config.timeout());
+                }
+            },
+            message = socket.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => {
+                        return Err(AppError::new(ErrorKind::Network, "Realtime connection closed by server"));
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(socket_error(e)),
+                };
+                let event: Value = serde_json::from_str(&text)
+                    .map_err(|e| AppError::new(ErrorKind::ParseError, format!("Invalid realtime event: {}", e)))?;
+                if handle_event(&event, state)? {
+                    on_partial(&state.text());
+                }
+                if state.audio_done && !state.pending() {
+                    break;
+                }
+            },
+            _ = sleep_until(deadline) => {
+                return Err(AppError::new(ErrorKind::Timeout, "Timed out waiting for the final transcript"));
+            }
+        }
+    }
+
+    let _ = socket.close(None).await;
+    Ok(())
+}
+
+// Commits the audio after the last server-side commit; returns false when nothing is left to wait for.
+async fn finish(socket: &mut Socket, state: &mut SessionState) -> Result<bool, AppError> {
+    if state.sent - state.committed_end >= MIN_COMMIT_BYTES {
+        send(socket, &serde_json::json!({ "type": "input_audio_buffer.commit" })).await?;
+        state.awaiting_commit = true;
+    }
+    Ok(state.pending())
+}
+
+// Applies one server event; returns true when the transcript changed.
+fn handle_event(event: &Value, state: &mut SessionState) -> Result<bool, AppError> {
+    let item_id = event["item_id"].as_str().unwrap_or_default();
+
+    match event["type"].as_str().unwrap_or_default() {
+        "input_audio_buffer.speech_stopped" => {
+            if let Some(ms) = event["audio_end_ms"].as_u64() {
+                state.speech_end = Some(state.connection_start + ms as usize * BYTES_PER_MS);
+            }
+        }
+        "input_audio_buffer.committed" => {
+            // 服务端自动提交前会先发 speech_stopped；没有时是录音结束后我们发出的提交，覆盖剩余的全部音频
+            if state.speech_end.is_none() {
+                state.awaiting_commit = false;
+            }
+            state.segment_mut(item_id);
+        }
+        "conversation.item.input_audio_transcription.delta" => {
+            let delta = event["delta"].as_str().unwrap_or_default();
+            state.segment_mut(item_id).text.push_str(delta);
+            return Ok(true);
+        }
+        "conversation.item.input_audio_transcription.completed" => {
+            state.complete(item_id, event["transcript"].as_str().unwrap_or_default());
+            return Ok(true);
+        }
+        "conversation.item.input_audio_transcription.failed" => {
+            // 单段失败不影响其他段，全部为空时才报错
+            let message = event["error"]["message"].as_str().unwrap_or("Transcription failed");
+            state.failure = Some(AppError {
+                provider_message: Some(message.to_string()),
+                ..AppError::new(ErrorKind::InvalidAudio, format!("Realtime transcription failed: {}", message))
+            });
+            state.complete(item_id, "");
+            return Ok(true);
+        }
+        "error" => {
+            let error = &event["error"];
+            let code = error["code"].as_str().unwrap_or_default();
+            // 最后一段已经被服务端自动提交，缓冲区为空
+            if code == "input_audio_buffer_commit_empty" {
+                state.awaiting_commit = false;
+                return Ok(false);
+            }
+            return Err(event_error(error));
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+async fn connect(config: &AIConfig) -> Result<Socket, AppError> {
+    let mut request = config
+        .base_url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| AppError::new(ErrorKind::InvalidRequest, format!("Invalid realtime endpoint {}: {}", config.base_url, e)))?;
+
+    // 本地测试服务通常不设 API Key，这时不发送鉴权头
+    if !config.api_key.is_empty() {
+        let value = HeaderValue::from_str(&format!("Bearer {}", config.api_key))
+            .map_err(|_| AppError::new(ErrorKind::Auth, "API key contains invalid characters"))?;
+        request.headers_mut().insert("Authorization", value);
+    }
+    request.headers_mut().insert("OpenAI-Beta", HeaderValue::from_static("realtime=v1"));
+
+    let (socket, _) = tokio::time::timeout(config.timeout(), tokio_tungstenite::connect_async(request))
+        .await
+        .map_err(|_| AppError::new(ErrorKind::Timeout, "Timed out connecting to the realtime endpoint"))?
+        .map_err(socket_error)?;
+    Ok(socket)
+}
+
+async fn send(socket: &mut Socket, event: &Value) -> Result<(), AppError> {
+    socket.send(Message::Text(event.to_string())).await.map_err(socket_error)
+}
+
+fn append_event(pcm: &[u8]) -> Value {
+    serde_json::json!({
+        "type": "input_audio_buffer.append",
+        "audio": base64::engine::general_purpose::STANDARD.encode(pcm)
+    })
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn socket_error(e: tungstenite::Error) -> AppError {
+    match e {
+        // 握手被拒绝，按 HTTP 状态码归类（401 鉴权失败、404 地址错误等）
+        tungstenite::Error::Http(response) => {
+            let body = response.body().as_deref().map(String::from_utf8_lossy).unwrap_or_default();
+            AppError::from_status(response.status().as_u16(), &body)
+        }
+        tungstenite::Error::Url(e) => AppError::new(ErrorKind::InvalidRequest, format!("Invalid realtime endpoint: {}", e)),
+        e => AppError::new(ErrorKind::Network, format!("Realtime connection failed: {}", e)),
+    }
+}
+
+fn event_error(error: &Value) -> AppError {
+    let code = error["code"].as_str().unwrap_or_default();
+    let message = error["message"].as_str().unwrap_or("Unknown error");
+
+    let kind = match code {
+        "invalid_api_key" | "unauthorized" => ErrorKind::Auth,
+        "rate_limit_exceeded" => ErrorKind::RateLimited,
+        "insufficient_quota" => ErrorKind::QuotaExceeded,
+        _ if error["type"].as_str() == Some("server_error") => ErrorKind::ServerError,
+        _ => ErrorKind::InvalidRequest,
+    };
+
+    AppError {
+        provider_message: Some(message.to_string()),
+        ..AppError::new(kind, format!("Realtime session error ({}): {}", code, message))
+    }
+}
+
+fn to_pcm16_le(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|&s| ((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    type Server = WebSocketStream<TcpStream>;
+
+    // 0.2 秒的音频，超过提交所需的最短长度
+    const CHUNK: usize = 200 * BYTES_PER_MS;
+
+    async fn listen() -> (TcpListener, AIConfig) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = AIConfig {
+            provider: "openai-realtime".to_string(),
+            base_url: format!("ws://{}", listener.local_addr().unwrap()),
+            whisper_model: "gpt-4o-transcribe".to_string(),
+            retry: RetryPolicy { base_delay_ms: 10, max_delay_ms: 10, ..RetryPolicy::default() },
+            timeout_ms: 5_000,
+            ..AIConfig::default()
+        };
+        (listener, config)
+    }
+
+    async fn accept(listener: &TcpListener) -> Server {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = tokio_tungstenite::accept_async(stream).await.unwrap();
+        assert_eq!(receive(&mut server).await["type"], "transcription_session.update");
+        server
+    }
+
+    async fn receive(server: &mut Server) -> Value {
+        loop {
+            if let Message::Text(text) = server.next().await.unwrap().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    async fn expect(server: &mut Server, kind: &str) -> Value {
+        let event = receive(server).await;
+        assert_eq!(event["type"], kind, "{}", event);
+        event
+    }
+
+    async fn emit(server: &mut Server, event: Value) {
+        server.send(Message::Text(event.to_string())).await.unwrap();
+    }
+
+    fn committed(item_id: &str) -> Value {
+        serde_json::json!({ "type": "input_audio_buffer.committed", "item_id": item_id })
+    }
+
+    fn delta(item_id: &str, delta: &str) -> Value {
+        serde_json::json!({ "type": "conversation.item.input_audio_transcription.delta", "item_id": item_id, "delta": delta })
+    }
+
+    fn completed(item_id: &str, transcript: &str) -> Value {
+        serde_json::json!({ "type": "conversation.item.input_audio_transcription.completed", "item_id": item_id, "transcript": transcript })
+    }
+
+    fn speech_stopped(audio_end_ms: u64) -> Value {
+        serde_json::json!({ "type": "input_audio_buffer.speech_stopped", "audio_end_ms": audio_end_ms })
+    }
+
+    async fn with_timeout<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::time::timeout(Duration::from_secs(10), future).await.expect("realtime test timed out")
+    }
+
+    #[tokio::test]
+    async fn deltas_arrive_in_order() {
+        let (listener, config) = listen().await;
+        let (pcm_sender, pcm_receiver) = unbounded_channel();
+        pcm_sender.send(vec![0; CHUNK]).unwrap();
+        drop(pcm_sender);
+
+        let server = async {
+            let mut server = accept(&listener).await;
+            expect(&mut server, "input_audio_buffer.append").await;
+            expect(&mut server, "input_audio_buffer.commit").await;
+            emit(&mut server, committed("a")).await;
+            for part in ["帮我", "写一个", "函数"] {
+                emit(&mut server, delta("a", part)).await;
+            }
+            emit(&mut server, completed("a", "帮我写一个函数")).await;
+            server
+        };
+        let partials = Arc::new(Mutex::new(Vec::new()));
+        let mut on_partial = {
+            let partials = partials.clone();
+            move |text: &str| partials.lock().unwrap().push(text.to_string())
+        };
+
+        let (_server, text) = with_timeout(async { tokio::join!(server, run(&config, pcm_receiver, &mut on_partial)) }).await;
+        assert_eq!(text.unwrap(), "帮我写一个函数");
+        assert_eq!(*partials.lock().unwrap(), ["帮我", "帮我写一个", "帮我写一个函数", "帮我写一个函数"]);
+    }
+
+    #[tokio::test]
+    async fn reconnect_keeps_segments() {
+        let (listener, config) = listen().await;
+        let (pcm_sender, pcm_receiver) = unbounded_channel();
+
+        let server = async {
+            // 第一段转写完成后，第二段说到一半时连接断开
+            let mut server = accept(&listener).await;
+            pcm_sender.send(vec![1; CHUNK]).unwrap();
+            expect(&mut server, "input_audio_buffer.append").await;
+            emit(&mut server, speech_stopped(200)).await;
+            emit(&mut server, committed("a")).await;
+            emit(&mut server, completed("a", "第一段。")).await;
+            pcm_sender.send(vec![2; CHUNK]).unwrap();
+            expect(&mut server, "input_audio_buffer.append").await;
+            emit(&mut server, delta("b", "第二")).await;
+            drop(server);
+
+            // 重连后只补发第二段的音频
+            let mut server = accept(&listener).await;
+            let replay = expect(&mut server, "input_audio_buffer.append").await;
+            let audio = base64::engine::general_purpose::STANDARD.decode(replay["audio"].as_str().unwrap()).unwrap();
+            assert_eq!(audio, vec![2; CHUNK]);
+            drop(pcm_sender);
+            expect(&mut server, "input_audio_buffer.commit").await;
+            emit(&mut server, committed("c")).await;
+            emit(&mut server, completed("c", "第二段。")).await;
+            server
+        };
+
+        let mut on_partial = |_: &str| {};
+        let (_server, text) = with_timeout(async { tokio::join!(server, run(&config, pcm_receiver, &mut on_partial)) }).await;
+        assert_eq!(text.unwrap(), "第一段。第二段。");
+    }
+
+    #[tokio::test]
+    async fn finishes_after_recording_stops() {
+        let (listener, config) = listen().await;
+        let (pcm_sender, pcm_receiver) = unbounded_channel();
+        let (first_delta, delta_received) = oneshot::channel();
+
+        let server = async {
+            // 服务端已经提交了全部音频，录音结束时没有剩余音频需要提交，只等最后一段转写完成
+            let mut server = accept(&listener).await;
+            pcm_sender.send(vec![0; CHUNK]).unwrap();
+            expect(&mut server, "input_audio_buffer.append").await;
+            emit(&mut server, speech_stopped(200)).await;
+            emit(&mut server, committed("a")).await;
+            emit(&mut server, delta("a", "最后")).await;
+            delta_received.await.unwrap();
+            drop(pcm_sender);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            emit(&mut server, completed("a", "最后一句。")).await;
+            server
+        };
+        let mut first_delta = Some(first_delta);
+        let mut on_partial = move |_: &str| {
+            if let Some(sender) = first_delta.take() {
+                let _ = sender.send(());
+            }
+        };
+
+        let (_server, text) = with_timeout(async { tokio::join!(server, run(&config, pcm_receiver, &mut on_partial)) }).await;
+        assert_eq!(text.unwrap(), "最后一句。");
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::{AppError, ErrorKind};
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// One entry of the personal replacement dictionary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReplacementRule {
+    pub from: String,
+    pub to: String,
+    /// from 是正则表达式，to 中可以用 $1、${name} 引用分组
+    pub regex: bool,
+    /// 只替换完整的单词：匹配两侧不能紧挨英文字母、数字或下划线，中文不受影响
+    pub whole_word: bool,
+    pub case_sensitive: bool,
+}
+
+impl Default for ReplacementRule {
+    fn default() -> Self {
+        Self {
+            from: String::new(),
+            to: String::new(),
+            regex: false,
+            whole_word: true,
+            case_sensitive: false,
+        }
+    }
+}
+
+/// Compiled rules, applied in order; each rule sees the output of the previous one.
+#[derive(Debug, Clone, Default)]
+pub struct Replacements {
+    rules: Vec<(Regex, ReplacementRule)>,
+}
+
+impl Replacements {
+    pub fn new(rules: &[ReplacementRule]) -> Result<Self, AppError> {
+        let rules = rules
+            .iter()
+            .filter(|rule| !rule.from.is_empty())
+            .map(|rule| {
+                let pattern = if rule.regex { rule.from.clone() } else { regex::escape(&rule.from) };
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(!rule.case_sensitive)
+                    .build()
+                    .map(|re| (re, rule.clone()))
+                    .map_err(|e| AppError::new(ErrorKind::Config, format!("Invalid replacement rule \"{}\": {}", rule.from, e)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (re, rule) in &self.rules {
+            let replaced = re.replace_all(&text, |caps: &Captures| {
+                let matched = caps.get(0).unwrap();
+                if rule.whole_word && !at_word_boundary(&text, matched.start(), matched.end()) {
+                    return matched.as_str().to_string();
+                }
+                if rule.regex {
+                    let mut out = String::new();
+                    caps.expand(&rule.to, &mut out);
+                    out
+                } else {
+                    rule.to.clone()
+                }
+            });
+            text = replaced.into_owned();
+        }
+        text
+    }
+}
+
+// 正则的 \b 把汉字也算作单词字符，"用kubernetes部署" 里 kubernetes 两侧没有 \b，所以只看英文字母和数字
+fn at_word_boundary(text: &str, start: usize, end: usize) -> bool {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    !text[..start].chars().next_back().is_some_and(is_word) && !text[end..].chars().next().is_some_and(is_word)
+}
+
+/// Reads a dictionary file written by `export`: a JSON array of rules.
+pub fn import(path: &Path) -> Result<Vec<ReplacementRule>, AppError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to read {}: {}", path.display(), e)))?;
+    let rules: Vec<ReplacementRule> = serde_json::from_str(&content)
+        .map_err(|e| AppError::new(ErrorKind::Config, format!("Invalid replacement file {}: {}", path.display(), e)))?;
+    // 导入前先检查正则是否有效
+    Replacements::new(&rules)?;
+    Ok(rules)
+}
+
+pub fn export(path: &Path, rules: &[ReplacementRule]) -> Result<(), AppError> {
+    let content = serde_json::to_string_pretty(rules)
+        .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to serialize replacement rules: {}", e)))?;
+    std::fs::write(path, content)
+        .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Adds imported rules to `rules`; an imported rule with the same `from` replaces the existing one.
+pub fn merge(rules: &mut Vec<ReplacementRule>, imported: Vec<ReplacementRule>) {
+    for rule in imported {
+        match rules.iter_mut().find(|existing| existing.from == rule.from && existing.regex == rule.regex) {
+            Some(existing) => *existing = rule,
+            None => rules.push(rule),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(from: &str, to: &str) -> ReplacementRule {
+        ReplacementRule { from: from.to_string(), to: to.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn applies_rules() {
+        let cases = [
+            (rule("k8s", "Kubernetes"), "用k8s部署", "用Kubernetes部署"),
+            (rule("k8s", "Kubernetes"), "K8S 集群", "Kubernetes 集群"),
+            (rule("k8s", "Kubernetes"), "k8sctl 和 mk8s", "k8sctl 和 mk8s"),
+            (rule("go", "Go"), "用 go 写，别用 google", "用 Go 写，别用 google"),
+            (rule("go", "Go"), "go_test 不变", "go_test 不变"),
+            (ReplacementRule { whole_word: false, ..rule("go", "Go") }, "google", "Google"),
+            (ReplacementRule { case_sensitive: true, ..rule("Rust", "Rust 语言") }, "rust 和 Rust", "rust 和 Rust 语言"),
+            (rule("c++", "C++"), "会写 c++ 吗", "会写 C++ 吗"),
+            (rule("微信", "WeChat"), "发到微信群", "发到WeChat群"),
+            (ReplacementRule { regex: true, ..rule(r"v(\d+)\.(\d+)", "版本 $1.$2") }, "升级到 v2.5", "升级到 版本 2.5"),
+            (rule("", "空规则"), "不变", "不变"),
+        ];
+        for (rule, text, expected) in cases {
+            let replacements = Replacements::new(std::slice::from_ref(&rule)).unwrap();
+            assert_eq!(replacements.apply(text), expected, "{} -> {}", rule.from, text);
+        }
+    }
+
+    #[test]
+    fn import_rejects_invalid_regex() {
+        let path = std::env::temp_dir().join(format!("voice2prompt-replacements-{}.json", std::process::id()));
+        let cases = [
+            (r#"[{"from": "k8s", "to": "Kubernetes"}, {"from": "v(\\d+)", "to": "版本 $1", "regex": true}]"#, Ok(2)),
+            (r#"[{"from": "(unclosed", "to": "x", "regex": true}]"#, Err(ErrorKind::Config)),
+            (r#"[{"from": "(unclosed", "to": "x"}]"#, Ok(1)),
+            (r#"{"from": "k8s"}"#, Err(ErrorKind::Config)),
+        ];
+        for (content, expected) in cases {
+            std::fs::write(&path, content).unwrap();
+            let actual = import(&path).map(|rules| rules.len()).map_err(|e| e.kind);
+            assert_eq!(actual, expected, "{}", content);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn merge_replaces_same_rule() {
+        let mut rules = vec![rule("k8s", "K8s"), rule("微信", "WeChat")];
+        let regex = ReplacementRule { regex: true, ..rule("k8s", "Kubernetes") };
+        merge(&mut rules, vec![rule("k8s", "Kubernetes"), regex, rule("vue", "Vue"), rule("vue", "Vue.js")]);
+
+        let merged: Vec<(&str, &str, bool)> = rules.iter().map(|r| (r.from.as_str(), r.to.as_str(), r.regex)).collect();
+        assert_eq!(merged, [
+            ("k8s", "Kubernetes", false),
+            ("微信", "WeChat", false),
+            ("k8s", "Kubernetes", true),
+            ("vue", "Vue.js", false),
+        ]);
+    }
+}
@@ -0,0 +1,242 @@
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use std::time::{Duration, Instant, SystemTime};
+
+/// 重试策略，只应用于可以安全重复发送的请求（转写、格式化）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// 从第一次发送开始计算的总时限，超过后不再重试
+    pub deadline_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+            deadline_ms: 60_000,
+        }
+    }
+}
+
+/// Sends the request built by `build`, retrying on rate limits and transient failures.
+///
+/// `build` is called once per attempt because multipart bodies can't be cloned.
+/// When retries are exhausted the last response (or error) is returned as-is so
+/// the caller can report the provider's error body.
+pub async fn send_with_retry<F>(policy: &RetryPolicy, build: F) -> Result<Response, reqwest::Error>
+where
+    F: FnMut() -> RequestBuilder,
+{
+    send(policy, build, true).await
+}
+
+/// Sends a request that creates something on the provider (an upload or a billed job).
+///
+/// Only connection failures are retried: the request never reached the server, so
+/// sending it again can't create a duplicate. Timeouts and error statuses are returned.
+pub async fn send_with_connect_retry<F>(policy: &RetryPolicy, build: F) -> Result<Response, reqwest::Error>
+where
+    F: FnMut() -> RequestBuilder,
+{
+    send(policy, build, false).await
+}
+
+async fn send<F>(policy: &RetryPolicy, mut build: F, idempotent: bool) -> Result<Response, reqwest::Error>
+where
+    F: FnMut() -> RequestBuilder,
+{
+    let started = Instant::now();
+    let deadline = Duration::from_millis(policy.deadline_ms);
+    let mut attempt = 0;
+
+    loop {
+        let result = build().send().await;
+
+        let delay = match &result {
+            Ok(response) if !idempotent || !is_retryable_status(response.status()) => return result,
+            Ok(response) => server_delay(response.headers())
+                .unwrap_or_else(|| backoff_delay(policy, attempt)),
+            Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => backoff_delay(policy, attempt),
+            Err(_) => return result,
+        };
+
+        attempt += 1;
+        if attempt > policy.max_retries || started.elapsed() + delay > deadline {
+            return result;
+        }
+
+        match &result {
+            Ok(response) => eprintln!(
+                "Request failed with status {}, retry {}/{} in {:?}",
+                response.status(), attempt, policy.max_retries, delay
+            ),
+            Err(e) => eprintln!(
+                "Request failed: {}, retry {}/{} in {:?}",
+                e, attempt, policy.max_retries, delay
+            ),
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    // 529 是 Anthropic 的 overloaded
+    matches!(status.as_u16(), 408 | 425 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+// Exponential backoff with "equal jitter": half of the window is fixed, half is random.
+pub fn backoff_delay(policy: &RetryPolicy, attempt: u32) -> Duration {
+    let window = policy
+        .base_delay_ms
+        .saturating_mul(1u64 << attempt.min(16))
+        .min(policy.max_delay_ms);
+    let half = window / 2;
+    let jitter = rand::thread_rng().gen_range(0..=half);
+    Duration::from_millis(half + jitter)
+}
+
+// How long the provider asked us to wait, if it said so.
+fn server_delay(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    // OpenAI / Azure 会额外返回毫秒精度的 retry-after-ms
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Some(Duration::from_millis(ms.max(0.0) as u64));
+    }
+
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<u64>() {
+            return Some(Duration::from_secs(secs));
+        }
+        if let Ok(at) = httpdate::parse_http_date(value) {
+            return Some(at.duration_since(SystemTime::now()).unwrap_or_default());
+        }
+    }
+
+    // x-ratelimit-remaining-* 为 0 的那一项，等到它对应的 reset 时间
+    ["requests", "tokens"]
+        .iter()
+        .filter(|bucket| header(&format!("x-ratelimit-remaining-{}", bucket)) == Some("0"))
+        .filter_map(|bucket| header(&format!("x-ratelimit-reset-{}", bucket)))
+        .filter_map(parse_reset_duration)
+        .max()
+}
+
+// Parses reset values such as "20ms", "1.5s", "6m0s" or a plain number of seconds.
+fn parse_reset_duration(value: &str) -> Option<Duration> {
+    if let Ok(secs) = value.parse::<f64>() {
+        return Some(Duration::from_secs_f64(secs.max(0.0)));
+    }
+
+    let mut total = 0.0;
+    let mut number = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let amount: f64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'h' => amount * 3600.0,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                amount / 1000.0
+            }
+            'm' => amount * 60.0,
+            's' => amount,
+            _ => return None,
+        };
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+    Some(Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn reset_durations() {
+        let cases = [
+            ("6m0s", Some(Duration::from_secs(360))),
+            ("20ms", Some(Duration::from_millis(20))),
+            ("1.5s", Some(Duration::from_millis(1500))),
+            ("1h2m3s", Some(Duration::from_secs(3723))),
+            ("2", Some(Duration::from_secs(2))),
+            ("0.25", Some(Duration::from_millis(250))),
+            ("5x", None),
+            ("10", Some(Duration::from_secs(10))),
+            ("s", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_reset_duration(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn numeric_retry_after() {
+        assert_eq!(server_delay(&headers(&[("retry-after", "7")])), Some(Duration::from_secs(7)));
+        // retry-after-ms 优先
+        assert_eq!(
+            server_delay(&headers(&[("retry-after", "7"), ("retry-after-ms", "1500")])),
+            Some(Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn http_date_retry_after() {
+        let at = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let delay = server_delay(&headers(&[("retry-after", &at)])).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60), "{:?}", delay);
+
+        // 已经过去的时间不用等
+        let past = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(60));
+        assert_eq!(server_delay(&headers(&[("retry-after", &past)])), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn rate_limit_reset_headers() {
+        let delay = server_delay(&headers(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "6m0s"),
+            ("x-ratelimit-remaining-tokens", "100"),
+            ("x-ratelimit-reset-tokens", "1h"),
+        ]));
+        assert_eq!(delay, Some(Duration::from_secs(360)));
+        assert_eq!(server_delay(&headers(&[("x-ratelimit-reset-requests", "20ms")])), None);
+    }
+
+    #[test]
+    fn backoff_bounds() {
+        let policy = RetryPolicy { base_delay_ms: 500, max_delay_ms: 8_000, ..RetryPolicy::default() };
+        for attempt in 0..40 {
+            let window = (500u64 << attempt.min(16)).min(8_000);
+            for _ in 0..50 {
+                let delay = backoff_delay(&policy, attempt).as_millis() as u64;
+                assert!(delay >= window / 2 && delay <= window, "attempt {}: {} not in {}..={}", attempt, delay, window / 2, window);
+            }
+        }
+    }
+}
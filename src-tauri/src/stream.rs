@@ -0,0 +1,189 @@
+use crate::error::{AppError, ErrorKind};
+use reqwest::Response;
+use serde_json::Value;
+use std::time::Duration;
+
+/// 接收流式输出的增量文本
+pub type DeltaSink<'a> = &'a mut (dyn FnMut(&str) + Send);
+
+/// OpenAI 兼容接口（含 Azure）的 SSE：`data: {"choices":[{"delta":{"content":"..."}}]}`
+pub async fn collect_openai_sse(response: Response, idle_timeout: Duration, on_delta: DeltaSink<'_>) -> Result<String, AppError> {
+    let mut text = String::new();
+    for_each_sse_data(response, idle_timeout, |data| {
+        if data == "[DONE]" {
+            return Ok(false);
+        }
+        let json = parse_event(data)?;
+        if !json["error"].is_null() {
+            return Err(stream_error(&json));
+        }
+        // Azure 的第一个事件只有 prompt_filter_results，choices 为空
+        if let Some(delta) = json["choices"][0]["delta"]["content"].as_str() {
+            text.push_str(delta);
+            on_delta(delta);
+        }
+        Ok(true)
+    })
+    .await?;
+    Ok(text)
+}
+
+/// Anthropic Messages 的 SSE：文本在 `content_block_delta` 事件的 `delta.text` 里
+pub async fn collect_anthropic_sse(response: Response, idle_timeout: Duration, on_delta: DeltaSink<'_>) -> Result<String, AppError> {
+    let mut text = String::new();
+    for_each_sse_data(response, idle_timeout, |data| {
+        let json = parse_event(data)?;
+        match json["type"].as_str() {
+            Some("content_block_delta") => {
+                if let Some(delta) = json["delta"]["text"].as_str() {
+                    text.push_str(delta);
+                    on_delta(delta);
+                }
+                Ok(true)
+            }
+            Some("message_stop") => Ok(false),
+            Some("error") => Err(stream_error(&json)),
+            _ => Ok(true),
+        }
+    })
+    .await?;
+    Ok(text)
+}
+
+/// Gemini `streamGenerateContent?alt=sse`：每个事件是一个完整的 GenerateContentResponse
+pub async fn collect_gemini_sse(response: Response, idle_timeout: Duration, on_delta: DeltaSink<'_>) -> Result<String, AppError> {
+    let mut text = String::new();
+    for_each_sse_data(response, idle_timeout, |data| {
+        let json = parse_event(data)?;
+        if !json["error"].is_null() {
+            return Err(stream_error(&json));
+        }
+        if let Some(parts) = json["candidates"][0]["content"]["parts"].as_array() {
+            for delta in parts.iter().filter_map(|part| part["text"].as_str()) {
+                text.push_str(delta);
+                on_delta(delta);
+            }
+        }
+        Ok(true)
+    })
+    .await?;
+    Ok(text)
+}
+
+/// Ollama `/api/chat` 的 NDJSON：每行一个 `{"message":{"content":"..."},"done":false}`
+pub async fn collect_ollama_ndjson(response: Response, idle_timeout: Duration, on_delta: DeltaSink<'_>) -> Result<String, AppError> {
+    let mut text = String::new();
+    for_each_ndjson(response, idle_timeout, |json| {
+        if let Some(delta) = json["message"]["content"].as_str() {
+            text.push_str(delta);
+            on_delta(delta);
+        }
+        Ok(!json["done"].as_bool().unwrap_or(false))
+    })
+    .await?;
+    Ok(text)
+}
+
+/// Calls `on_event` with every JSON line of an NDJSON body until it returns false.
+///
+/// A line carrying an `error` field ends the stream with that error.
+pub async fn for_each_ndjson<F>(response: Response, idle_timeout: Duration, mut on_event: F) -> Result<(), AppError>
+where
+    F: FnMut(&Value) -> Result<bool, AppError>,
+{
+    for_each_line(response, idle_timeout, |line| {
+        if line.trim().is_empty() {
+            return Ok(true);
+        }
+        let json = parse_event(line)?;
+        if !json["error"].is_null() {
+            return Err(stream_error(&json));
+        }
+        on_event(&json)
+    })
+    .await
+}
+
+// Calls `on_data` with the joined `data:` lines of every SSE event until it returns false.
+async fn for_each_sse_data<F>(response: Response, idle_timeout: Duration, mut on_data: F) -> Result<(), AppError>
+where
+    F: FnMut(&str) -> Result<bool, AppError>,
+{
+    let mut data = String::new();
+    for_each_line(response, idle_timeout, |line| {
+        if line.is_empty() {
+            // 空行表示一个事件结束
+            if data.is_empty() {
+                return Ok(true);
+            }
+            let keep_going = on_data(&data)?;
+            data.clear();
+            return Ok(keep_going);
+        }
+        if let Some(value) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(value.strip_prefix(' ').unwrap_or(value));
+        }
+        // event:、id:、retry: 以及 ":" 开头的注释行都不需要
+        Ok(true)
+    })
+    .await?;
+
+    // 有些服务在最后一个事件后不发空行
+    if !data.is_empty() {
+        on_data(&data)?;
+    }
+    Ok(())
+}
+
+// Splits the response body into lines, waiting at most `idle_timeout` for each chunk.
+async fn for_each_line<F>(mut response: Response, idle_timeout: Duration, mut on_line: F) -> Result<(), AppError>
+where
+    F: FnMut(&str) -> Result<bool, AppError>,
+{
+    let mut buffer: Vec<u8> = Vec::new();
+    loop {
+        let chunk = tokio::time::timeout(idle_timeout, response.chunk())
+            .await
+            .map_err(|_| AppError::new(ErrorKind::Timeout, "Stream stalled: no data received"))??;
+
+        let chunk = match chunk {
+            Some(chunk) => chunk,
+            None => break,
+        };
+        buffer.extend_from_slice(&chunk);
+
+        // 按字节切行，避免把跨块的多字节 UTF-8 字符切坏
+        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            if !on_line(line.trim_end_matches(['\r', '\n']))? {
+                return Ok(());
+            }
+        }
+    }
+
+    if !buffer.is_empty() {
+        let line = String::from_utf8_lossy(&buffer);
+        on_line(line.trim_end_matches(['\r', '\n']))?;
+    }
+    Ok(())
+}
+
+fn parse_event(data: &str) -> Result<Value, AppError> {
+    serde_json::from_str(data)
+        .map_err(|e| AppError::new(ErrorKind::ParseError, format!("Failed to parse stream event: {}", e)))
+}
+
+fn stream_error(json: &Value) -> AppError {
+    let message = json["error"]["message"]
+        .as_str()
+        .or_else(|| json["error"].as_str())
+        .unwrap_or("Unknown stream error");
+    AppError {
+        provider_message: Some(message.to_string()),
+        ..AppError::new(ErrorKind::ServerError, format!("Stream failed: {}", message))
+    }
+}
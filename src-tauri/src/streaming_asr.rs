@@ -0,0 +1,151 @@
+use crate::audio::{self, AIConfig, AudioFrame};
+use crate::error::{AppError, ErrorKind};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use vosk::{DecodingState, Model, Recognizer};
+
+/// Vosk 小模型使用 16kHz 输入
+const VOSK_SAMPLE_RATE: u32 = 16_000;
+
+// 当前录音对应的识别线程，录音结束后由 transcribe 取走结果
+static SESSION: Mutex<Option<JoinHandle<Result<String, AppError>>>> = Mutex::new(None);
+static MODEL: Mutex<Option<(String, Arc<Model>)>> = Mutex::new(None);
+
+/// Starts a live recognition session and returns the sender to pass to `audio::start_recording`.
+///
+/// `on_partial` receives the whole transcript so far (finalized sentences plus the
+/// current hypothesis) every time it changes. The session ends when the recording
+/// stops and the sender is dropped.
+pub fn start<F>(model_path: &str, mut on_partial: F) -> Result<mpsc::Sender<AudioFrame>, AppError>
+where
+    F: FnMut(&str) + Send + 'static,
+{
+    let model = load_model(model_path)?;
+    let mut recognizer = new_recognizer(&model)?;
+    let (sender, receiver) = mpsc::channel::<AudioFrame>();
+
+    let worker = std::thread::spawn(move || {
+        let mut finished = String::new();
+        let mut last_partial = String::new();
+
+        // 发送端（录音回调）被释放后 recv 返回错误，循环结束
+        while let Ok(frame) = receiver.recv() {
+            let mono = audio::downmix(&frame.samples, frame.channels);
+            let pcm = to_pcm16(&audio::resample(&mono, frame.sample_rate, VOSK_SAMPLE_RATE));
+
+            match accept(&mut recognizer, &pcm)? {
+                DecodingState::Finalized => {
+                    let sentence = recognizer.result().single().map(|r| join_words(r.text)).unwrap_or_default();
+                    finished.push_str(&sentence);
+                    on_partial(&finished);
+                }
+                _ => {
+                    let partial = join_words(recognizer.partial_result().partial);
+                    if partial != last_partial {
+                        on_partial(&format!("{}{}", finished, partial));
+                        last_partial = partial;
+                    }
+                }
+            }
+        }
+
+        let tail = recognizer.final_result().single().map(|r| join_words(r.text)).unwrap_or_default();
+        finished.push_str(&tail);
+        Ok(finished)
+    });
+
+    *SESSION.lock().unwrap() = Some(worker);
+    Ok(sender)
+}
+
+/// Returns the transcript of the live session if one ran, otherwise recognizes the file.
+pub async fn transcribe(file_path: String, config: AIConfig) -> Result<String, AppError> {
+    let worker = SESSION.lock().unwrap().take();
+    let model_path = config.whisper_model.clone();
+
+    let text = tokio::task::spawn_blocking(move || match worker {
+        Some(worker) => worker
+            .join()
+            .map_err(|_| AppError::new(ErrorKind::LocalModel, "Streaming recognizer thread panicked"))?,
+        None => recognize_file(&model_path, &file_path),
+    })
+    .await
+    .map_err(|e| AppError::new(ErrorKind::LocalModel, format!("Recognizer task failed: {}", e)))??;
+
+    Ok(text)
+}
+
+fn recognize_file(model_path: &str, file_path: &str) -> Result<String, AppError> {
+    let model = load_model(model_path)?;
+    let mut recognizer = new_recognizer(&model)?;
+    let samples = to_pcm16(&audio::read_wav_mono(file_path, VOSK_SAMPLE_RATE)?);
+
+    let mut text = String::new();
+    // 按 0.2 秒分块送入，和实时识别时的行为一致
+    for chunk in samples.chunks(VOSK_SAMPLE_RATE as usize / 5) {
+        if accept(&mut recognizer, chunk)? == DecodingState::Finalized {
+            if let Some(result) = recognizer.result().single() {
+                text.push_str(&join_words(result.text));
+            }
+        }
+    }
+    if let Some(result) = recognizer.final_result().single() {
+        text.push_str(&join_words(result.text));
+    }
+    Ok(text)
+}
+
+fn load_model(model_path: &str) -> Result<Arc<Model>, AppError> {
+    let mut cached = MODEL.lock().unwrap();
+    if let Some((path, model)) = cached.as_ref() {
+        if path == model_path {
+            return Ok(model.clone());
+        }
+    }
+
+    let model = Model::new(model_path)
+        .map(Arc::new)
+        .ok_or_else(|| AppError::new(ErrorKind::LocalModel, format!("Failed to load Vosk model: {}", model_path)))?;
+    *cached = Some((model_path.to_string(), model.clone()));
+    Ok(model)
+}
+
+fn new_recognizer(model: &Model) -> Result<Recognizer, AppError> {
+    Recognizer::new(model, VOSK_SAMPLE_RATE as f32)
+        .ok_or_else(|| AppError::new(ErrorKind::LocalModel, "Failed to create Vosk recognizer"))
+}
+
+fn accept(recognizer: &mut Recognizer, pcm: &[i16]) -> Result<DecodingState, AppError> {
+    match recognizer.accept_waveform(pcm) {
+        Ok(DecodingState::Failed) => Err(AppError::new(ErrorKind::InvalidAudio, "Vosk failed to decode audio")),
+        Ok(state) => Ok(state),
+        Err(e) => Err(AppError::new(ErrorKind::InvalidAudio, format!("Vosk rejected audio: {}", e))),
+    }
+}
+
+fn to_pcm16(samples: &[f32]) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect()
+}
+
+// 中文模型按词输出并以空格分隔，汉字之间的空格需要去掉，英文单词之间保留
+fn join_words(text: &str) -> String {
+    let mut out = String::new();
+    for word in text.split_whitespace() {
+        let needs_space = match (out.chars().last(), word.chars().next()) {
+            (Some(prev), Some(next)) => !is_cjk(prev) && !is_cjk(next),
+            _ => false,
+        };
+        if needs_space {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    out
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x3000..=0x303F | 0xFF00..=0xFFEF)
+}
@@ -0,0 +1,268 @@
+use crate::audio::{self, AIConfig};
+use crate::error::{AppError, ErrorKind};
+use crate::retry::send_with_retry;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::SystemTime;
+
+/// 一句话识别只接受 8k/16k 音频，统一转成 16kHz 单声道 WAV
+const TENCENT_SAMPLE_RATE: u32 = 16_000;
+
+/// 一句话识别的音频时长上限
+const MAX_SECONDS: usize = 60;
+
+const SERVICE: &str = "asr";
+const ACTION: &str = "SentenceRecognition";
+const VERSION: &str = "2019-06-14";
+const CONTENT_TYPE: &str = "application/json; charset=utf-8";
+
+/// Transcribes with Tencent Cloud ASR SentenceRecognition (audio up to 60 seconds).
+///
+/// `config.api_key` is the SecretId, `config.api_secret` the SecretKey and
+/// `config.whisper_model` the engine type such as `16k_zh`.
+pub async fn transcribe(client: &reqwest::Client, file_path: String, config: AIConfig) -> Result<String, AppError> {
+    let secret_key = secret_key(&config)?;
+
+    let samples = audio::read_wav_mono(&file_path, TENCENT_SAMPLE_RATE)?;
+    // 超过 60 秒服务端会拒绝，上传之前先检查
+    if samples.len() > MAX_SECONDS * TENCENT_SAMPLE_RATE as usize {
+        return Err(AppError::new(
+            ErrorKind::InvalidAudio,
+            format!(
+                "Tencent Cloud sentence recognition accepts at most {} seconds of audio, the recording is {} seconds",
+                MAX_SECONDS,
+                samples.len() / TENCENT_SAMPLE_RATE as usize
+            ),
+        ));
+    }
+    let wav = audio::encode_wav_mono(&samples, TENCENT_SAMPLE_RATE)?;
+
+    let payload = serde_json::json!({
+        "EngSerViceType": config.whisper_model,
+        "SourceType": 1,
+        "VoiceFormat": "wav",
+        "Data": base64::engine::general_purpose::STANDARD.encode(&wav),
+        "DataLen": wav.len(),
+        "FilterDirty": 0,
+        "FilterModal": 0,
+        "ConvertNumMode": 1
+    })
+    .to_string();
+
+    let result = call(client, &config, secret_key, ACTION, &payload).await?;
+    if !result["Error"].is_null() {
+        return Err(api_error(&result["Error"]));
+    }
+
+    let text = result["Result"]
+        .as_str()
+        .ok_or_else(|| AppError::new(ErrorKind::ParseError, "No text in response"))?
+        .to_string();
+
+    Ok(text)
+}
+
+/// Checks the SecretId and SecretKey by asking for the status of a task that does not exist.
+pub async fn probe(client: &reqwest::Client, config: &AIConfig) -> Result<(), AppError> {
+    let secret_key = secret_key(config)?;
+    let result = call(client, config, secret_key, "DescribeTaskStatus", r#"{"TaskId":0}"#).await?;
+    // 签名通过后才会检查任务 id，任务不存在的错误说明密钥有效
+    let code = result["Error"]["Code"].as_str().unwrap_or_default();
+    if code.starts_with("AuthFailure") || code.starts_with("UnauthorizedOperation") {
+        return Err(api_error(&result["Error"]));
+    }
+    Ok(())
+}
+
+fn secret_key(config: &AIConfig) -> Result<&str, AppError> {
+    config
+        .api_secret
+        .as_deref()
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| AppError::new(ErrorKind::Auth, "Tencent Cloud SecretKey is not set"))
+}
+
+// Sends a signed ASR API call and returns the `Response` object.
+// 腾讯云 API 的业务错误以 HTTP 200 返回，错误信息在 Response.Error 里，由调用方检查
+async fn call(client: &reqwest::Client, config: &AIConfig, secret_key: &str, action: &str, payload: &str) -> Result<Value, AppError> {
+    let url = reqwest::Url::parse(&config.base_url)
+        .map_err(|e| AppError::new(ErrorKind::InvalidRequest, format!("Invalid base URL: {}", e)))?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        _ => return Err(AppError::new(ErrorKind::InvalidRequest, "Invalid base URL: missing host")),
+    };
+
+    let response = send_with_retry(&config.retry, || {
+        // 签名包含时间戳，每次重试都重新签名
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let authorization = sign(&config.api_key, secret_key, SERVICE, &host, Some(action), payload, timestamp);
+
+        client
+            .post(url.clone())
+            .timeout(config.timeout())
+            .header("Authorization", authorization)
+            .header("Content-Type", CONTENT_TYPE)
+            .header("Host", &host)
+            .header("X-TC-Action", action)
+            .header("X-TC-Version", VERSION)
+            .header("X-TC-Timestamp", timestamp.to_string())
+            .body(payload.to_string())
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_response(response).await);
+    }
+
+    let mut json: Value = response.json().await?;
+    Ok(json["Response"].take())
+}
+
+// Builds the TC3-HMAC-SHA256 Authorization header for a POST with a JSON body.
+// `action` is added to the signed headers when given.
+// See https://cloud.tencent.com/document/api/1093/35641
+fn sign(secret_id: &str, secret_key: &str, service: &str, host: &str, action: Option<&str>, payload: &str, timestamp: u64) -> String {
+    let date = utc_date(timestamp);
+    let mut canonical_headers = format!("content-type:{}\nhost:{}\n", CONTENT_TYPE, host);
+    let mut signed_headers = "content-type;host".to_string();
+    if let Some(action) = action {
+        canonical_headers.push_str(&format!("x-tc-action:{}\n", action.to_lowercase()));
+        signed_headers.push_str(";x-tc-action");
+    }
+    let canonical_request = format!(
+        "POST\n/\n\n{}\n{}\n{}",
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(payload.as_bytes())),
+    );
+
+    let credential_scope = format!("{}/{}/tc3_request", date, service);
+    let string_to_sign = format!(
+        "TC3-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        credential_scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes())),
+    );
+
+    let secret_date = hmac_sha256(format!("TC3{}", secret_key).as_bytes(), &date);
+    let secret_service = hmac_sha256(&secret_date, service);
+    let secret_signing = hmac_sha256(&secret_service, "tc3_request");
+    let signature = hex::encode(hmac_sha256(&secret_signing, &string_to_sign));
+
+    format!(
+        "TC3-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        secret_id, credential_scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// UTC calendar date (YYYY-MM-DD) of a Unix timestamp, used in the credential scope.
+fn utc_date(timestamp: u64) -> String {
+    // Howard Hinnant 的 civil_from_days 算法
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn api_error(error: &Value) -> AppError {
+    let code = error["Code"].as_str().unwrap_or_default();
+    let message = error["Message"].as_str().unwrap_or("Unknown error");
+
+    let kind = if code.starts_with("AuthFailure") || code.starts_with("UnauthorizedOperation") {
+        ErrorKind::Auth
+    } else if code.starts_with("RequestLimitExceeded") || code.starts_with("LimitExceeded") {
+        ErrorKind::RateLimited
+    } else if code.contains("UserHasNoAmount") || code.contains("NoFreeAmount") || code.starts_with("ResourceUnavailable") {
+        ErrorKind::QuotaExceeded
+    } else if code.starts_with("FailedOperation.ErrorDownFile") || code.contains("Audio") || code.contains("Data") {
+        ErrorKind::InvalidAudio
+    } else if code.starts_with("InvalidParameter") || code.starts_with("MissingParameter") || code.starts_with("UnsupportedOperation") {
+        ErrorKind::InvalidRequest
+    } else {
+        ErrorKind::ServerError
+    };
+
+    AppError {
+        // 业务错误的 HTTP 状态码都是 200
+        status: Some(200),
+        provider_message: Some(message.to_string()),
+        ..AppError::new(kind, format!("Tencent Cloud ASR failed ({}): {}", code, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 腾讯云签名文档中的示例请求（CVM DescribeInstances）
+    #[test]
+    fn published_signature() {
+        let payload = r#"{"Limit": 1, "Filters": [{"Values": ["\u672a\u547d\u540d"], "Name": "instance-name"}]}"#;
+        let authorization = sign(
+            "AKIDz8krbsJ5yKBZQpn74WFkmLPx3EXAMPLE",
+            "Gu5t9xGARNpq86cd98joQYCN3EXAMPLE",
+            "cvm",
+            "cvm.tencentcloudapi.com",
+            None,
+            payload,
+            1551113065,
+        );
+        assert_eq!(
+            authorization,
+            "TC3-HMAC-SHA256 Credential=AKIDz8krbsJ5yKBZQpn74WFkmLPx3EXAMPLE/2019-02-25/cvm/tc3_request, \
+             SignedHeaders=content-type;host, \
+             Signature=72e494ea809ad7a8c8f7a4507b9bddcbaa8e581f516e8da2f66e2c5a96525168"
+        );
+    }
+
+    #[test]
+    fn signs_action_header() {
+        let authorization = sign("id", "key", SERVICE, "asr.tencentcloudapi.com", Some(ACTION), "{}", 1551113065);
+        assert!(authorization.contains("/2019-02-25/asr/tc3_request, SignedHeaders=content-type;host;x-tc-action, "));
+    }
+
+    #[test]
+    fn utc_dates() {
+        let cases = [
+            (0, "1970-01-01"),
+            (1551113065, "2019-02-25"),
+            // 闰年的 2 月 29 日及其前后
+            (951782400 - 1, "2000-02-28"),
+            (951782400, "2000-02-29"),
+            (951868800, "2000-03-01"),
+            (1582934400, "2020-02-29"),
+            (1583020800 - 1, "2020-02-29"),
+            (1583020800, "2020-03-01"),
+            (1709164800, "2024-02-29"),
+            (1735603200 + 86_399, "2024-12-31"),
+            (1735603200 + 86_400, "2025-01-01"),
+            // 平年
+            (1677628800 - 1, "2023-02-28"),
+            (1677628800, "2023-03-01"),
+            // 2100 年不是闰年
+            (4107456000, "2100-02-28"),
+            (4107542400, "2100-03-01"),
+        ];
+        for (timestamp, expected) in cases {
+            assert_eq!(utc_date(timestamp), expected, "{}", timestamp);
+        }
+    }
+}
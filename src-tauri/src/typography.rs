@@ -0,0 +1,321 @@
+use serde::Deserialize;
+
+/// Typography rules for mixed Chinese/Latin text, applied to the formatted result.
+///
+/// Every rule defaults to `Keep`, which leaves the text as the model wrote it.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TypographyOptions {
+    pub spacing: Spacing,
+    pub punctuation: Punctuation,
+    pub quotes: Quotes,
+}
+
+/// 汉字与英文字母、数字之间的空格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Spacing {
+    #[default]
+    Keep,
+    /// "用Rust写" -> "用 Rust 写"
+    Add,
+    /// "用 Rust 写" -> "用Rust写"
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Punctuation {
+    #[default]
+    Keep,
+    /// 中文句子里的半角标点改为全角："好的," -> "好的，"
+    FullWidth,
+    /// 全角标点改为半角加空格："好的，" -> "好的, "
+    HalfWidth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Quotes {
+    #[default]
+    Keep,
+    /// “” 和 ‘’
+    Curly,
+    /// 「」 和 『』
+    Corner,
+}
+
+// (半角, 全角)
+const PUNCTUATION: &[(char, char)] = &[
+    (',', '，'),
+    ('.', '。'),
+    ('!', '！'),
+    ('?', '？'),
+    (':', '：'),
+    (';', '；'),
+    ('(', '（'),
+    (')', '）'),
+];
+
+pub fn apply(text: &str, options: &TypographyOptions) -> String {
+    if options.spacing == Spacing::Keep && options.punctuation == Punctuation::Keep && options.quotes == Quotes::Keep {
+        return text.to_string();
+    }
+
+    // 代码块和行内代码原样保留
+    let mut out = String::with_capacity(text.len());
+    for (segment, is_code) in split_code(text) {
+        if is_code {
+            out.push_str(segment);
+            continue;
+        }
+        let segment = quotes(segment, options.quotes);
+        let segment = punctuation(&segment, options.punctuation);
+        out.push_str(&spacing(&segment, options.spacing));
+    }
+    out
+}
+
+fn is_han(c: char) -> bool {
+    matches!(c,
+        '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}' | '\u{3040}'..='\u{30ff}')
+}
+
+fn is_latin(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+}
+
+// 按 ``` 代码块和 ` 行内代码切分，返回 (片段, 是否为代码)
+fn split_code(text: &str) -> Vec<(&str, bool)> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('`') {
+        let fence = if rest[start..].starts_with("```") { "```" } else { "`" };
+        let Some(end) = rest[start + fence.len()..].find(fence).map(|end| start + fence.len() + end + fence.len()) else {
+            break;
+        };
+        segments.push((&rest[..start], false));
+        segments.push((&rest[start..end], true));
+        rest = &rest[end..];
+    }
+    segments.push((rest, false));
+    segments
+}
+
+fn spacing(text: &str, spacing: Spacing) -> String {
+    if spacing == Spacing::Keep {
+        return text.to_string();
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        match spacing {
+            Spacing::Add => {
+                if let Some(&prev) = i.checked_sub(1).and_then(|p| chars.get(p)) {
+                    if (is_han(prev) && is_latin(c)) || (is_latin(prev) && is_han(c)) {
+                        out.push(' ');
+                    }
+                }
+                out.push(c);
+            }
+            _ => {
+                // 只删掉汉字和英文之间的单个空格，连续空格多半是有意的对齐
+                let between = c == ' '
+                    && i > 0
+                    && chars.get(i + 1).is_some_and(|&next| {
+                        let prev = chars[i - 1];
+                        (is_han(prev) && is_latin(next)) || (is_latin(prev) && is_han(next))
+                    });
+                if !between {
+                    out.push(c);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn punctuation(text: &str, punctuation: Punctuation) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+
+    match punctuation {
+        Punctuation::Keep => text.to_string(),
+        Punctuation::FullWidth => {
+            for (i, &c) in chars.iter().enumerate() {
+                // 全角字母和数字一律改为半角
+                if ('\u{ff01}'..='\u{ff5e}').contains(&c) && char::from_u32(c as u32 - 0xfee0).is_some_and(is_latin) {
+                    out.push(char::from_u32(c as u32 - 0xfee0).unwrap());
+                    continue;
+                }
+                let Some(&(_, full)) = PUNCTUATION.iter().find(|(half, _)| *half == c) else {
+                    out.push(c);
+                    continue;
+                };
+                let prev = out.trim_end_matches(' ').chars().next_back();
+                let next = chars[i + 1..].iter().copied().find(|&n| n != ' ');
+                // 紧挨汉字才算中文句子里的标点；句号只看前面，"3.5"、"e.g." 不受影响
+                let in_chinese = match c {
+                    '(' => next.is_some_and(is_han),
+                    ')' => prev.is_some_and(is_han),
+                    // 省略号 "..." 保持原样
+                    '.' => next != Some('.') && prev.is_some_and(is_han),
+                    _ => prev.is_some_and(is_han) || next.is_some_and(is_han),
+                };
+                if in_chinese {
+                    let trimmed = out.trim_end_matches(' ').len();
+                    out.truncate(trimmed);
+                    out.push(full);
+                } else {
+                    out.push(c);
+                }
+            }
+            // 全角标点后面不需要空格
+            let mut tidy = String::with_capacity(out.len());
+            let mut after_full = false;
+            for c in out.chars() {
+                if c == ' ' && after_full {
+                    continue;
+                }
+                after_full = is_punctuation(c) && !c.is_ascii();
+                tidy.push(c);
+            }
+            tidy
+        }
+        Punctuation::HalfWidth => {
+            for (i, &c) in chars.iter().enumerate() {
+                let Some(&(half, _)) = PUNCTUATION.iter().find(|(_, full)| *full == c) else {
+                    out.push(c);
+                    continue;
+                };
+                out.push(half);
+                // 句中的逗号、句号等后面补一个空格
+                let next = chars.get(i + 1).copied();
+                if !matches!(half, '(' | ')') && next.is_some_and(|n| !n.is_whitespace() && !is_punctuation(n)) {
+                    out.push(' ');
+                }
+            }
+            out
+        }
+    }
+}
+
+fn is_punctuation(c: char) -> bool {
+    PUNCTUATION.iter().any(|&(half, full)| c == half || c == full)
+}
+
+fn quotes(text: &str, quotes: Quotes) -> String {
+    let (open, close, inner_open, inner_close) = match quotes {
+        Quotes::Keep => return text.to_string(),
+        Quotes::Curly => ('“', '”', '‘', '’'),
+        Quotes::Corner => ('「', '」', '『', '』'),
+    };
+
+    // 直引号只在引号内有汉字时改写，英文里的 "quoted" 保持原样
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('"') {
+        let Some(end) = rest[start + 1..].find(['"', '\n']).map(|len| start + 1 + len) else {
+            break;
+        };
+        if !rest[end..].starts_with('"') {
+            // 换行前没有配对的引号
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        let inner = &rest[start + 1..end];
+        if inner.chars().any(is_han) {
+            out.push_str(&rest[..start]);
+            out.push(open);
+            out.push_str(inner);
+            out.push(close);
+        } else {
+            out.push_str(&rest[..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+
+    let out = convert_pairs(&out, ['“', '「'], ['”', '」'], open, close);
+    convert_pairs(&out, ['‘', '『'], ['’', '』'], inner_open, inner_close)
+}
+
+// 已有的弯引号和直角引号同样只在引号内有汉字时改写，纯英文的 “quoted” 保持原样
+fn convert_pairs(text: &str, opens: [char; 2], closes: [char; 2], open: char, close: char) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+    // 英文里的撇号（don’t）不是引号
+    let is_apostrophe = |chars: &[char], i: usize| {
+        chars[i] == '’' && i > 0 && is_latin(chars[i - 1]) && chars.get(i + 1).is_some_and(|&n| is_latin(n))
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        if !opens.contains(&chars[i]) {
+            i += 1;
+            continue;
+        }
+        let end = (i + 1..chars.len())
+            .take_while(|&j| chars[j] != '\n')
+            .find(|&j| closes.contains(&chars[j]) && !is_apostrophe(&chars, j));
+        match end {
+            Some(end) if chars[i + 1..end].iter().copied().any(is_han) => {
+                chars[i] = open;
+                chars[end] = close;
+                i = end + 1;
+            }
+            _ => i += 1,
+        }
+    }
+    chars.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_typography() {
+        let options = |spacing, punctuation, quotes| TypographyOptions { spacing, punctuation, quotes };
+        let keep = options(Spacing::Keep, Punctuation::Keep, Quotes::Keep);
+        let add = options(Spacing::Add, Punctuation::Keep, Quotes::Keep);
+        let remove = options(Spacing::Remove, Punctuation::Keep, Quotes::Keep);
+        let full = options(Spacing::Keep, Punctuation::FullWidth, Quotes::Keep);
+        let half = options(Spacing::Keep, Punctuation::HalfWidth, Quotes::Keep);
+        let curly = options(Spacing::Keep, Punctuation::Keep, Quotes::Curly);
+        let corner = options(Spacing::Keep, Punctuation::Keep, Quotes::Corner);
+
+        let cases = [
+            (keep, "用Rust写了3个服务", "用Rust写了3个服务"),
+            // 空格
+            (add, "用Rust写了3个服务", "用 Rust 写了 3 个服务"),
+            (add, "用 Rust 写", "用 Rust 写"),
+            (remove, "用 Rust 写了 3 个服务", "用Rust写了3个服务"),
+            (remove, "Rust  写", "Rust  写"),
+            (add, "运行 `cargo build`之后", "运行 `cargo build`之后"),
+            // 标点
+            (full, "好的,我们开始吧!", "好的，我们开始吧！"),
+            (full, "版本是3.5.", "版本是3.5."),
+            (full, "等等...", "等等..."),
+            (full, "例如 e.g. 这样", "例如 e.g. 这样"),
+            (full, "Hello, world!", "Hello, world!"),
+            (full, "共ＡＢＣ１２３个", "共ABC123个"),
+            (half, "好的，我们开始吧！", "好的, 我们开始吧!"),
+            // 引号
+            (curly, r#"他说"你好""#, "他说“你好”"),
+            (curly, r#"say "hello" to him"#, r#"say "hello" to him"#),
+            (curly, "他说「你好」", "他说“你好”"),
+            (corner, "他说“你好”", "他说「你好」"),
+            (corner, "他说‘你好’", "他说『你好』"),
+            (corner, "She said “hello” and left", "She said “hello” and left"),
+            (corner, "It’s a ‘test’ case", "It’s a ‘test’ case"),
+            (corner, "“don’t 忘了”", "「don’t 忘了」"),
+            (corner, "“没有配对的引号", "“没有配对的引号"),
+        ];
+        for (options, text, expected) in cases {
+            assert_eq!(apply(text, &options), expected, "{:?} {}", options, text);
+        }
+    }
+}
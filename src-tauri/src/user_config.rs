@@ -0,0 +1,114 @@
+use crate::compat::CompatProvider;
+use crate::error::{AppError, ErrorKind};
+use crate::prompts::PromptTemplate;
+use crate::replacements::ReplacementRule;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// 应用配置目录下的 config.json，保存界面上不方便编辑的高级设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UserConfig {
+    /// 自定义的 OpenAI 兼容服务商
+    pub custom_providers: Vec<CompatProvider>,
+    /// 自定义的格式化提示词模板，与内置模板同 id 时覆盖内置模板
+    pub prompt_templates: Vec<PromptTemplate>,
+    /// 专有名词表，填入提示词的 {{glossary}}
+    pub glossary: Vec<String>,
+    /// 个人替换词典，转写结果和最终结果各应用一次
+    pub replacements: Vec<ReplacementRule>,
+}
+
+impl UserConfig {
+    /// Loads the config file; a missing file is the same as an empty config.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to read {}: {}", path.display(), e)))?;
+        let config: Self = serde_json::from_str(&content)
+            .map_err(|e| AppError::new(ErrorKind::Config, format!("Invalid config file {}: {}", path.display(), e)))?;
+
+        if let Some(provider) = config.custom_providers.iter().find(|p| p.id.is_empty()) {
+            return Err(AppError::new(
+                ErrorKind::Config,
+                format!("Custom provider \"{}\" in {} has no id", provider.name, path.display()),
+            ));
+        }
+        if let Some(template) = config.prompt_templates.iter().find(|t| t.id.is_empty()) {
+            return Err(AppError::new(
+                ErrorKind::Config,
+                format!("Prompt template \"{}\" in {} has no id", template.name, path.display()),
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Writes `replacements` back to the config file, leaving every other key as the user wrote it.
+    pub fn save_replacements(&self, path: &Path) -> Result<(), AppError> {
+        // 按原始 JSON 只改这一项，不认识的字段和自定义服务商里的额外字段都不会丢
+        let mut json = if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to read {}: {}", path.display(), e)))?;
+            serde_json::from_str(&content)
+                .map_err(|e| AppError::new(ErrorKind::Config, format!("Invalid config file {}: {}", path.display(), e)))?
+        } else {
+            Value::Object(Default::default())
+        };
+        let replacements = serde_json::to_value(&self.replacements)
+            .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to serialize config: {}", e)))?;
+        json.as_object_mut()
+            .ok_or_else(|| AppError::new(ErrorKind::Config, format!("Invalid config file {}: not a JSON object", path.display())))?
+            .insert("replacements".to_string(), replacements);
+
+        let content = serde_json::to_string_pretty(&json)
+            .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to serialize config: {}", e)))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to create {}: {}", dir.display(), e)))?;
+        }
+        std::fs::write(path, content)
+            .map_err(|e| AppError::new(ErrorKind::Config, format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    pub fn custom_provider(&self, id: &str) -> Option<&CompatProvider> {
+        self.custom_providers.iter().find(|p| p.id == id)
+    }
+
+    pub fn prompt_template(&self, id: &str) -> Option<&PromptTemplate> {
+        self.prompt_templates.iter().find(|t| t.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_keeps_other_keys() {
+        let dir = std::env::temp_dir().join(format!("voice2prompt-config-{}", std::process::id()));
+        let path = dir.join("config.json");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, r#"{
+            "glossary": ["Tauri"],
+            "customProviders": [{"id": "local", "name": "Local", "baseUrl": "http://localhost:8080/v1", "note": "自己加的说明"}],
+            "futureSetting": {"enabled": true},
+            "replacements": [{"from": "旧", "to": "新"}]
+        }"#).unwrap();
+
+        let mut config = UserConfig::load(&path).unwrap();
+        config.replacements.push(ReplacementRule { from: "k8s".to_string(), to: "Kubernetes".to_string(), ..Default::default() });
+        config.save_replacements(&path).unwrap();
+
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(saved["glossary"], serde_json::json!(["Tauri"]));
+        assert_eq!(saved["customProviders"][0]["note"], "自己加的说明");
+        assert_eq!(saved["futureSetting"]["enabled"], true);
+        assert_eq!(saved["replacements"].as_array().unwrap().len(), 2);
+        assert_eq!(saved["replacements"][1]["to"], "Kubernetes");
+    }
+}
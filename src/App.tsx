@@ -1,8 +1,10 @@
-import React, { useState, useEffect } from 'react';
+import React, { useState, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/tauri';
 import { listen } from '@tauri-apps/api/event';
+import { open, save } from '@tauri-apps/api/dialog';
 import './styles.css';
 import './modal-styles.css';
+import defaults from './config/defaults.json';
 
 interface AIProvider {
   id: string;
@@ -12,10 +14,13 @@ interface AIProvider {
   whisperModel: string;
   gptModel: string;
   supportsWhisper: boolean;
+  // 本地服务不需要 API Key
+  requiresApiKey?: boolean;
 }
 
 interface AppState {
   isRecording: boolean;
+  isProcessing: boolean;
   transcribedText: string;
   formattedText: string;
   status: string;
@@ -26,6 +31,10 @@ interface AppState {
   whisperModel: string;
   speechApiVersion: string;
   whisperDeployment: string;
+  // 腾讯云 SecretKey（API Key 一栏填写 SecretId）
+  speechApiSecret: string;
+  // 'true' 时在转写结果上做数字规范化（二零二六年 -> 2026年）
+  speechItn: string;
   // 文本处理配置
   textProvider: string;
   textApiKey: string;
@@ -33,8 +42,97 @@ interface AppState {
   gptModel: string;
   textApiVersion: string;
   gptDeployment: string;
+  // 输出模式（Rust 端 modes::OutputMode），每次录音前可切换
+  outputMode: string;
+  // 提示词模板 id，为空时使用输出模式对应的内置模板
+  promptTemplate: string;
+  // 翻译模式：源语言为空时自动识别；bilingual 为 'true' 时同时输出原文和译文
+  sourceLanguage: string;
+  targetLanguage: string;
+  bilingual: string;
+  // 中英文排版规范化（Rust 端 typography::TypographyOptions），keep 表示不处理
+  typographySpacing: string;
+  typographyPunctuation: string;
+  typographyQuotes: string;
+  // 处理流程：separate 先转写再格式化，single 把录音直接发给支持音频输入的对话模型
+  pipelineMode: string;
+  // Ollama 原生接口选项
+  ollamaKeepAlive: string;
+  ollamaNumCtx: string;
+  ollamaTemperature: string;
+  // Azure Entra ID 鉴权（企业租户禁用了 API Key 时使用）
+  azureAuthMode: string;
+  azureTenantId: string;
+  azureClientId: string;
+  azureClientSecret: string;
+  azureTokenEndpoint: string;
+  // 网络配置
+  networkProxy: string;
+  caCertificates: string;
+  // 失败重试，留空使用 Rust 端的默认值
+  retryMaxRetries: string;
+  retryDeadline: string;
 }
 
+// Rust 端 error::AppError 的序列化结构
+interface AppError {
+  kind: string;
+  message: string;
+  status: number | null;
+  providerMessage: string | null;
+  hint: string;
+}
+
+// Rust 端 discovery::ConnectionReport
+interface ConnectionReport {
+  ok: boolean;
+  checked: boolean;
+  reachable: boolean;
+  authenticated: boolean;
+  modelFound: boolean | null;
+  suggestions: string[];
+  latencyMs: number;
+  error: AppError | null;
+}
+
+// Rust 端 user_config::UserConfig 中前端用到的部分
+interface UserConfig {
+  customProviders: {
+    id: string;
+    name: string;
+    baseUrl: string;
+    whisperModel: string;
+    gptModel: string;
+    transcriptionPath: string | null;
+  }[];
+  replacements: { from: string; to: string }[];
+}
+
+const isAppError = (error: unknown): error is AppError =>
+  typeof error === 'object' && error !== null && 'kind' in error && 'hint' in error;
+
+const describeError = (error: unknown): string => {
+  if (!isAppError(error)) {
+    return String(error);
+  }
+  const detail = error.providerMessage || error.message;
+  return `${error.hint}（${detail}）`;
+};
+
+const OUTPUT_MODES = [
+  { id: 'clean', name: '中文清理' },
+  { id: 'verbatim', name: '原样输出' },
+  { id: 'markdown', name: 'Markdown' },
+  { id: 'email', name: '正式邮件' },
+  { id: 'commit', name: 'Git 提交信息' },
+  { id: 'summary', name: '要点摘要' },
+  { id: 'prompt', name: '结构化提示词' },
+  { id: 'translate', name: '翻译' }
+];
+
+// 翻译模式可选的语言，值直接填入提示词
+const TRANSLATE_LANGUAGES = ['简体中文', '繁體中文', 'English', '日本語', '한국어', 'Français', 'Deutsch', 'Español'];
+
 const AI_PROVIDERS: AIProvider[] = [
   {
     id: 'openai',
@@ -90,20 +188,97 @@ const AI_PROVIDERS: AIProvider[] = [
     gptModel: 'claude-3-haiku-20240307',
     supportsWhisper: false
   },
+  {
+    id: 'gemini',
+    name: 'Google Gemini',
+    description: 'Gemini 多模态模型，直接识别音频并清理文本',
+    baseUrl: 'https://generativelanguage.googleapis.com/v1beta',
+    whisperModel: 'gemini-2.0-flash',
+    gptModel: 'gemini-2.0-flash',
+    supportsWhisper: true
+  },
+  {
+    id: 'dashscope',
+    name: '阿里云百炼',
+    description: 'DashScope Paraformer 录音文件识别（仅支持语音识别）',
+    baseUrl: 'https://dashscope.aliyuncs.com',
+    whisperModel: 'paraformer-v2',
+    gptModel: '',
+    supportsWhisper: true
+  },
+  {
+    id: 'tencent',
+    name: '腾讯云语音识别',
+    description: '一句话识别，最长 60 秒（仅支持语音识别，API Key 填写 SecretId）',
+    baseUrl: 'https://asr.tencentcloudapi.com',
+    whisperModel: '16k_zh',
+    gptModel: '',
+    supportsWhisper: true
+  },
+  {
+    id: 'assemblyai',
+    name: 'AssemblyAI',
+    description: '上传后异步转写，自动区分说话人（仅支持语音识别）',
+    baseUrl: 'https://api.assemblyai.com',
+    whisperModel: 'universal',
+    gptModel: '',
+    supportsWhisper: true
+  },
   {
     id: 'ollama',
     name: 'Ollama 本地',
-    description: '本地部署的 Ollama 服务',
+    description: '本地部署的 Ollama 服务（仅支持文本格式化）',
     baseUrl: 'http://localhost:11434',
-    whisperModel: 'whisper:latest',
+    whisperModel: '',
     gptModel: 'llama3:latest',
+    supportsWhisper: false,
+    requiresApiKey: false
+  },
+  {
+    id: 'whisper-local',
+    name: 'Whisper 离线',
+    description: '内置 whisper.cpp 本地识别（需以 local-whisper 特性构建）',
+    baseUrl: '',
+    whisperModel: 'ggml-base.bin',
+    gptModel: '',
+    supportsWhisper: true,
+    requiresApiKey: false
+  },
+  {
+    id: 'openai-realtime',
+    name: 'OpenAI Realtime',
+    description: '通过 WebSocket 边录边转写，兼容 OpenAI Realtime 协议的服务都可使用（仅支持语音识别）',
+    baseUrl: 'wss://api.openai.com/v1/realtime?intent=transcription',
+    whisperModel: 'gpt-4o-transcribe',
+    gptModel: '',
     supportsWhisper: true
+  },
+  {
+    id: 'vosk',
+    name: 'Vosk 实时离线',
+    description: '边说边出字的本地流式识别（需以 local-vosk 特性构建）',
+    baseUrl: '',
+    whisperModel: 'vosk-model-small-cn-0.22',
+    gptModel: '',
+    supportsWhisper: true,
+    requiresApiKey: false
+  },
+  {
+    id: 'llama-local',
+    name: 'GGUF 离线模型',
+    description: '内置 llama.cpp 本地文本格式化（仅支持文本格式化，需以 local-llm 特性构建）',
+    baseUrl: '',
+    whisperModel: '',
+    gptModel: 'qwen2.5-1.5b-instruct-q4_k_m.gguf',
+    supportsWhisper: false,
+    requiresApiKey: false
   }
 ];
 
 function App() {
   const [state, setState] = useState<AppState>({
     isRecording: false,
+    isProcessing: false,
     transcribedText: '',
     formattedText: '',
     status: '准备就绪',
@@ -114,22 +289,110 @@ function App() {
     whisperModel: localStorage.getItem('whisper_model') || 'whisper-1',
     speechApiVersion: localStorage.getItem('speech_api_version') || '2024-02-01',
     whisperDeployment: localStorage.getItem('whisper_deployment') || 'whisper',
+    speechApiSecret: localStorage.getItem('speech_api_secret') || '',
+    speechItn: localStorage.getItem('speech_itn') || 'false',
     // 文本处理配置
     textProvider: localStorage.getItem('text_provider') || 'openai',
     textApiKey: localStorage.getItem('text_api_key') || '',
     textBaseUrl: localStorage.getItem('text_base_url') || 'https://api.openai.com/v1',
     gptModel: localStorage.getItem('gpt_model') || 'gpt-4o-mini',
     textApiVersion: localStorage.getItem('text_api_version') || '2024-02-01',
-    gptDeployment: localStorage.getItem('gpt_deployment') || 'gpt-4'
+    gptDeployment: localStorage.getItem('gpt_deployment') || 'gpt-4',
+    outputMode: localStorage.getItem('output_mode') || 'clean',
+    promptTemplate: localStorage.getItem('prompt_template') || '',
+    sourceLanguage: localStorage.getItem('source_language') || '',
+    targetLanguage: localStorage.getItem('target_language') || 'English',
+    bilingual: localStorage.getItem('bilingual') || 'false',
+    typographySpacing: localStorage.getItem('typography_spacing') || 'keep',
+    typographyPunctuation: localStorage.getItem('typography_punctuation') || 'keep',
+    typographyQuotes: localStorage.getItem('typography_quotes') || 'keep',
+    pipelineMode: localStorage.getItem('pipeline_mode') || 'separate',
+    // Ollama 原生接口选项
+    ollamaKeepAlive: localStorage.getItem('ollama_keep_alive') || '',
+    ollamaNumCtx: localStorage.getItem('ollama_num_ctx') || '',
+    ollamaTemperature: localStorage.getItem('ollama_temperature') || '',
+    // Azure Entra ID 鉴权
+    azureAuthMode: localStorage.getItem('azure_auth_mode') || 'apiKey',
+    azureTenantId: localStorage.getItem('azure_tenant_id') || '',
+    azureClientId: localStorage.getItem('azure_client_id') || '',
+    azureClientSecret: localStorage.getItem('azure_client_secret') || '',
+    azureTokenEndpoint: localStorage.getItem('azure_token_endpoint') || '',
+    // 网络配置
+    networkProxy: localStorage.getItem('network_proxy') || '',
+    caCertificates: localStorage.getItem('ca_certificates') || '',
+    retryMaxRetries: localStorage.getItem('retry_max_retries') || '',
+    retryDeadline: localStorage.getItem('retry_deadline') || ''
   });
 
   const [showSettings, setShowSettings] = useState(false);
   const [showSpeechDialog, setShowSpeechDialog] = useState(false);
   const [showTextDialog, setShowTextDialog] = useState(false);
   const [showShortcutDialog, setShowShortcutDialog] = useState(false);
+  // 「获取模型列表」得到的模型，作为模型输入框的候选项
+  const [speechModels, setSpeechModels] = useState<string[]>([]);
+  const [textModels, setTextModels] = useState<string[]>([]);
+  const [providers, setProviders] = useState<AIProvider[]>(AI_PROVIDERS);
+  const [promptTemplates, setPromptTemplates] = useState<{ id: string; name: string }[]>([]);
+  const [replacementCount, setReplacementCount] = useState(0);
+  // 文本服务出错、改用本地规则清理时的错误说明，在完成提示里显示
+  const cleanupFallback = useRef<string | null>(null);
+
+  const currentSpeechProvider = providers.find(p => p.id === state.speechProvider) || providers[0];
+  const currentTextProvider = providers.find(p => p.id === state.textProvider) || providers[0];
+
+  // 单次请求模式需要支持音频输入的对话接口（gpt-4o-audio、Gemini 等），自定义服务商由用户自行保证
+  const supportsSingleCall = (providerId: string) =>
+    ['openai', 'deepseek', 'zhipu', 'moonshot', 'gemini'].includes(providerId) ||
+    !AI_PROVIDERS.some(provider => provider.id === providerId);
+  const usesSingleCall = state.pipelineMode === 'single' && supportsSingleCall(state.textProvider);
+  const usesEntraId = (providerId: string) => providerId === 'azure' && state.azureAuthMode === 'entraId';
+  const azureAuth = (providerId: string) => usesEntraId(providerId) ? {
+    tenantId: state.azureTenantId,
+    clientId: state.azureClientId,
+    clientSecret: state.azureClientSecret,
+    tokenEndpoint: state.azureTokenEndpoint || null
+  } : null;
+  const needsSpeechApiKey = currentSpeechProvider.requiresApiKey !== false && !usesEntraId(state.speechProvider);
 
-  const currentSpeechProvider = AI_PROVIDERS.find(p => p.id === state.speechProvider) || AI_PROVIDERS[0];
-  const currentTextProvider = AI_PROVIDERS.find(p => p.id === state.textProvider) || AI_PROVIDERS[0];
+  useEffect(() => {
+    // 配置文件 config.json 中的自定义 OpenAI 兼容服务商追加到内置列表之后
+    invoke<UserConfig>('reload_user_config')
+      .then(config => {
+        const custom = config.customProviders.map(p => ({
+          id: p.id,
+          name: p.name || p.id,
+          description: '自定义 OpenAI 兼容服务',
+          baseUrl: p.baseUrl,
+          whisperModel: p.whisperModel,
+          gptModel: p.gptModel,
+          supportsWhisper: Boolean(p.transcriptionPath),
+          // 本地服务通常不需要 API Key，需要时在设置里填写
+          requiresApiKey: false
+        }));
+        // 与内置服务商同 id 的条目替换内置条目
+        const builtIn = AI_PROVIDERS.map(p => custom.find(c => c.id === p.id) || p);
+        setProviders([...builtIn, ...custom.filter(c => !AI_PROVIDERS.some(p => p.id === c.id))]);
+        setReplacementCount(config.replacements.length);
+        return invoke<{ id: string; name: string }[]>('list_prompt_templates');
+      })
+      .then(setPromptTemplates)
+      .catch(error => {
+        setState(prev => ({ ...prev, status: `读取配置文件失败: ${describeError(error)}` }));
+      });
+  }, []);
+
+  useEffect(() => {
+    // 代理和根证书在 Rust 端的共享 HTTP 客户端上生效
+    invoke('set_network_config', {
+      config: {
+        proxy: state.networkProxy || null,
+        caCertificates: state.caCertificates.split(',').map(p => p.trim()).filter(Boolean),
+        connectTimeoutMs: defaults.api.connectTimeout
+      }
+    }).catch(error => {
+      setState(prev => ({ ...prev, status: `网络配置无效: ${describeError(error)}` }));
+    });
+  }, [state.networkProxy, state.caCertificates]);
 
   useEffect(() => {
     // Listen for global shortcut events
@@ -154,16 +417,40 @@ function App() {
       setShowShortcutDialog(true);
     });
 
+    // 流式识别的中间结果，每次推送的是到目前为止的完整文本
+    const unlistenPartial = listen<string>('transcription-partial', event => {
+      setState(prev => ({ ...prev, transcribedText: event.payload }));
+    });
+
+    const unlistenPullProgress = listen<{ model: string; status: string; completed: number | null; total: number | null }>('ollama-pull-progress', event => {
+      const { model, status, completed, total } = event.payload;
+      const percent = completed && total ? ` ${Math.floor(completed * 100 / total)}%` : '';
+      setState(prev => ({ ...prev, status: `拉取 ${model}: ${status}${percent}` }));
+    });
+
+    // 流式格式化时逐段显示结果
+    const unlistenFormatDelta = listen<string>('format-text-delta', event => {
+      setState(prev => ({ ...prev, formattedText: prev.formattedText + event.payload }));
+    });
+
+    const unlistenFallback = listen<AppError>('format-text-fallback', event => {
+      cleanupFallback.current = describeError(event.payload);
+    });
+
     return () => {
       unlistenShortcut.then(fn => fn());
       unlistenSpeechSettings.then(fn => fn());
       unlistenTextSettings.then(fn => fn());
       unlistenShortcutSettings.then(fn => fn());
+      unlistenFormatDelta.then(fn => fn());
+      unlistenFallback.then(fn => fn());
+      unlistenPartial.then(fn => fn());
+      unlistenPullProgress.then(fn => fn());
     };
   }, [state.isRecording]);
 
   const handleStartRecording = async () => {
-    if (!state.speechApiKey) {
+    if (needsSpeechApiKey && !state.speechApiKey) {
       setState(prev => ({ ...prev, status: `请先设置语音识别 ${currentSpeechProvider.name} API Key` }));
       return;
     }
@@ -175,22 +462,76 @@ function App() {
 
     try {
       setState(prev => ({ ...prev, isRecording: true, status: '正在录音...' }));
-      await invoke('start_recording');
+      await invoke('start_recording', {
+        provider: state.speechProvider,
+        whisperModel: state.whisperModel,
+        apiKey: state.speechApiKey,
+        baseUrl: state.speechBaseUrl,
+        timeoutMs: defaults.api.timeout,
+        retry: retryPolicy()
+      });
     } catch (error) {
       setState(prev => ({ 
         ...prev, 
         isRecording: false, 
-        status: `录音失败: ${error}` 
+        status: `录音失败: ${describeError(error)}` 
       }));
     }
   };
 
+  const finishProcessing = async (formattedText: string) => {
+    setState(prev => ({ 
+      ...prev, 
+      formattedText, 
+      isProcessing: false,
+      status: '正在复制到剪贴板...' 
+    }));
+
+    // Copy to clipboard
+    await invoke('copy_to_clipboard', { text: formattedText });
+
+    const fallback = cleanupFallback.current;
+    cleanupFallback.current = null;
+    setState(prev => ({ 
+      ...prev, 
+      status: fallback
+        ? `文本服务不可用（${fallback}），已用本地规则清理并复制到剪贴板`
+        : '完成！已复制到剪贴板，可以 Ctrl+V 粘贴' 
+    }));
+
+    // Auto clear status after 3 seconds
+    setTimeout(() => {
+      setState(prev => ({ ...prev, status: '准备就绪' }));
+    }, 3000);
+  };
+
   const handleStopRecording = async () => {
     try {
-      setState(prev => ({ ...prev, status: '正在处理录音...' }));
+      setState(prev => ({ ...prev, isProcessing: true, status: '正在处理录音...' }));
       
       // Stop recording and get file path
       const filePath = await invoke<string>('stop_recording');
+
+      if (usesSingleCall) {
+        setState(prev => ({ ...prev, isRecording: false, transcribedText: '', formattedText: '', status: '正在识别并格式化...' }));
+
+        // 转写和格式化合并为一次对话请求，返回与 format_text 相同的结果
+        const formattedText = await invoke<string>('process_audio', {
+          filePath,
+          provider: state.textProvider,
+          apiKey: state.textApiKey,
+          baseUrl: state.textBaseUrl,
+          gptModel: state.gptModel,
+          timeoutMs: defaults.api.timeout,
+          retry: retryPolicy(),
+          stream: true,
+          prompt: promptOptions(),
+          typography: typographyOptions()
+        });
+        await finishProcessing(formattedText);
+        return;
+      }
+
       setState(prev => ({ ...prev, isRecording: false, status: '正在转写...' }));
 
       // Transcribe audio
@@ -203,12 +544,18 @@ function App() {
         gptModel: state.gptModel,
         apiVersion: state.speechProvider === 'azure' ? state.speechApiVersion : null,
         whisperDeployment: state.speechProvider === 'azure' ? state.whisperDeployment : null,
-        gptDeployment: state.speechProvider === 'azure' ? state.gptDeployment : null
+        apiSecret: state.speechProvider === 'tencent' ? state.speechApiSecret : null,
+        azureAuth: azureAuth(state.speechProvider),
+        gptDeployment: state.speechProvider === 'azure' ? state.gptDeployment : null,
+        timeoutMs: defaults.api.timeout,
+        retry: retryPolicy(),
+        itn: state.speechItn === 'true'
       });
       
       setState(prev => ({ 
         ...prev, 
         transcribedText, 
+        formattedText: '',
         status: '正在格式化...' 
       }));
 
@@ -222,40 +569,44 @@ function App() {
         gptModel: state.gptModel,
         apiVersion: state.textProvider === 'azure' ? state.textApiVersion : null,
         whisperDeployment: state.textProvider === 'azure' ? state.whisperDeployment : null,
-        gptDeployment: state.textProvider === 'azure' ? state.gptDeployment : null
+        gptDeployment: state.textProvider === 'azure' ? state.gptDeployment : null,
+        timeoutMs: defaults.api.timeout,
+        retry: retryPolicy(),
+        stream: true,
+        prompt: promptOptions(),
+        typography: typographyOptions(),
+        azureAuth: azureAuth(state.textProvider),
+        ollama: state.textProvider === 'ollama' ? {
+          native: true,
+          keepAlive: state.ollamaKeepAlive || null,
+          numCtx: state.ollamaNumCtx ? Number(state.ollamaNumCtx) : null,
+          temperature: state.ollamaTemperature ? Number(state.ollamaTemperature) : null
+        } : null
       });
 
-      setState(prev => ({ 
-        ...prev, 
-        formattedText, 
-        status: '正在复制到剪贴板...' 
-      }));
-
-      // Copy to clipboard
-      await invoke('copy_to_clipboard', { text: formattedText });
-      
-      setState(prev => ({ 
-        ...prev, 
-        status: '完成！已复制到剪贴板，可以 Ctrl+V 粘贴' 
-      }));
-
-      // Auto clear status after 3 seconds
-      setTimeout(() => {
-        setState(prev => ({ ...prev, status: '准备就绪' }));
-      }, 3000);
+      await finishProcessing(formattedText);
 
     } catch (error) {
       setState(prev => ({ 
         ...prev, 
         isRecording: false, 
-        status: `处理失败: ${error}` 
+        isProcessing: false,
+        status: isAppError(error) && error.kind === 'cancelled' ? '已取消' : `处理失败: ${describeError(error)}` 
       }));
     }
   };
 
+  const handleCancelProcessing = async () => {
+    try {
+      await invoke('cancel_processing');
+    } catch (error) {
+      setState(prev => ({ ...prev, status: `取消失败: ${describeError(error)}` }));
+    }
+  };
+
   const handleSpeechProviderChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
     const providerId = e.target.value;
-    const provider = AI_PROVIDERS.find(p => p.id === providerId);
+    const provider = providers.find(p => p.id === providerId);
     if (provider) {
       setState(prev => ({ 
         ...prev, 
@@ -271,7 +622,7 @@ function App() {
 
   const handleTextProviderChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
     const providerId = e.target.value;
-    const provider = AI_PROVIDERS.find(p => p.id === providerId);
+    const provider = providers.find(p => p.id === providerId);
     if (provider) {
       setState(prev => ({ 
         ...prev, 
@@ -285,6 +636,117 @@ function App() {
     }
   };
 
+  // 语音和文本两组设置共用的连接参数
+  const connectionArgs = (target: 'speech' | 'text') => target === 'speech'
+    ? {
+        provider: state.speechProvider,
+        apiKey: state.speechApiKey,
+        apiSecret: state.speechProvider === 'tencent' ? state.speechApiSecret : null,
+        baseUrl: state.speechBaseUrl,
+        apiVersion: state.speechProvider === 'azure' ? state.speechApiVersion : null,
+        azureAuth: azureAuth(state.speechProvider)
+      }
+    : {
+        provider: state.textProvider,
+        apiKey: state.textApiKey,
+        baseUrl: state.textBaseUrl,
+        apiVersion: state.textProvider === 'azure' ? state.textApiVersion : null,
+        azureAuth: azureAuth(state.textProvider)
+      };
+
+  const handleListModels = async (target: 'speech' | 'text') => {
+    try {
+      const models = await invoke<string[]>('list_models', connectionArgs(target));
+      (target === 'speech' ? setSpeechModels : setTextModels)(models);
+      setState(prev => ({ ...prev, status: `获取到 ${models.length} 个模型` }));
+    } catch (error) {
+      setState(prev => ({ ...prev, status: `获取模型列表失败: ${describeError(error)}` }));
+    }
+  };
+
+  // 替换词典文件是规则的 JSON 数组，导入时与 config.json 中的规则合并
+  const handleImportReplacements = async () => {
+    const path = await open({ filters: [{ name: 'JSON', extensions: ['json'] }] });
+    if (typeof path !== 'string') return;
+    try {
+      const count = await invoke<number>('import_replacements', { path });
+      setReplacementCount(count);
+      setState(prev => ({ ...prev, status: `已导入替换词典，共 ${count} 条规则` }));
+    } catch (error) {
+      setState(prev => ({ ...prev, status: `导入替换词典失败: ${describeError(error)}` }));
+    }
+  };
+
+  const handleExportReplacements = async () => {
+    const path = await save({ defaultPath: 'replacements.json', filters: [{ name: 'JSON', extensions: ['json'] }] });
+    if (!path) return;
+    try {
+      await invoke('export_replacements', { path });
+      setState(prev => ({ ...prev, status: `替换词典已导出到 ${path}` }));
+    } catch (error) {
+      setState(prev => ({ ...prev, status: `导出替换词典失败: ${describeError(error)}` }));
+    }
+  };
+
+  const handleTestConnection = async (target: 'speech' | 'text') => {
+    const provider = target === 'speech' ? state.speechProvider : state.textProvider;
+    // Azure 检查的是部署名称
+    const model = provider === 'azure'
+      ? (target === 'speech' ? state.whisperDeployment : state.gptDeployment)
+      : (target === 'speech' ? state.whisperModel : state.gptModel);
+
+    setState(prev => ({ ...prev, status: '正在测试连接...' }));
+    try {
+      const report = await invoke<ConnectionReport>('test_connection', { ...connectionArgs(target), model });
+      let status;
+      if (!report.checked) {
+        status = '本地引擎不需要测试连接';
+      } else if (report.ok) {
+        status = `连接正常（${report.latencyMs}ms）${report.modelFound ? `，模型 ${model} 可用` : ''}`;
+      } else if (report.modelFound === false) {
+        const suggestions = report.suggestions.length > 0 ? `，相近的模型: ${report.suggestions.join('、')}` : '';
+        status = `连接正常，但没有找到模型 ${model}${suggestions}`;
+      } else {
+        status = `连接失败: ${describeError(report.error)}`;
+      }
+      setState(prev => ({ ...prev, status }));
+    } catch (error) {
+      setState(prev => ({ ...prev, status: `连接失败: ${describeError(error)}` }));
+    }
+  };
+
+  const handlePullOllamaModel = async () => {
+    try {
+      await invoke('pull_ollama_model', { baseUrl: state.textBaseUrl, model: state.gptModel });
+      setState(prev => ({ ...prev, status: `模型 ${state.gptModel} 已就绪` }));
+      handleListModels('text');
+    } catch (error) {
+      setState(prev => ({ ...prev, status: `拉取模型失败: ${describeError(error)}` }));
+    }
+  };
+
+  // 对应 Rust 端 prompts::PromptOptions
+  const promptOptions = () => ({
+    mode: state.outputMode,
+    template: state.promptTemplate || null,
+    sourceLanguage: state.sourceLanguage || null,
+    targetLanguage: state.targetLanguage || null,
+    bilingual: state.bilingual === 'true'
+  });
+
+  // 对应 Rust 端 typography::TypographyOptions
+  // 只传填写了的字段，其余使用 retry::RetryPolicy 的默认值
+  const retryPolicy = () => ({
+    ...(state.retryMaxRetries ? { maxRetries: Number(state.retryMaxRetries) } : {}),
+    ...(state.retryDeadline ? { deadlineMs: Number(state.retryDeadline) * 1000 } : {})
+  });
+
+  const typographyOptions = () => ({
+    spacing: state.typographySpacing,
+    punctuation: state.typographyPunctuation,
+    quotes: state.typographyQuotes
+  });
+
   const handleConfigChange = (field: keyof AppState, value: string) => {
     setState(prev => ({ ...prev, [field]: value }));
     localStorage.setItem(field.replace(/([A-Z])/g, '_$1').toLowerCase(), value);
@@ -328,14 +790,35 @@ function App() {
         </div>
 
         <div className="controls">
+          <select
+            className="mode-select"
+            value={state.outputMode}
+            onChange={(e) => handleConfigChange('outputMode', e.target.value)}
+            disabled={state.isRecording || state.isProcessing}
+            title="输出模式"
+          >
+            {OUTPUT_MODES.map(mode => (
+              <option key={mode.id} value={mode.id}>{mode.name}</option>
+            ))}
+          </select>
+
           <button
             className={`record-btn ${state.isRecording ? 'recording' : ''}`}
             onClick={state.isRecording ? handleStopRecording : handleStartRecording}
-            disabled={!state.speechApiKey}
+            disabled={needsSpeechApiKey && !state.speechApiKey}
           >
             {state.isRecording ? '停止录音' : '开始录音'}
           </button>
 
+          {state.isProcessing && (
+            <button
+              className="cancel-btn"
+              onClick={handleCancelProcessing}
+            >
+              取消
+            </button>
+          )}
+
           <button
             className="settings-btn"
             onClick={() => setShowSettings(!showSettings)}
@@ -358,7 +841,7 @@ function App() {
                   onChange={handleSpeechProviderChange}
                   className="provider-select"
                 >
-                  {AI_PROVIDERS.map(provider => (
+                  {providers.map(provider => (
                     <option key={provider.id} value={provider.id}>
                       {provider.name} - {provider.description}
                     </option>
@@ -400,7 +883,33 @@ function App() {
                   placeholder="Whisper 模型"
                   className="config-input"
                   disabled={!currentSpeechProvider.supportsWhisper}
+                  list="speech-models"
                 />
+                <datalist id="speech-models">
+                  {speechModels.map(name => <option key={name} value={name} />)}
+                </datalist>
+              </div>
+
+              <div className="setting-group">
+                <label htmlFor="speech-itn">数字规范化:</label>
+                <select
+                  id="speech-itn"
+                  value={state.speechItn}
+                  onChange={(e) => handleConfigChange('speechItn', e.target.value)}
+                  className="config-input"
+                >
+                  <option value="false">关闭</option>
+                  <option value="true">开启（二零二六年十月十七号 → 2026年10月17日）</option>
+                </select>
+              </div>
+
+              <div className="setting-group">
+                <button onClick={() => handleListModels('speech')} className="btn-secondary">
+                  获取模型列表
+                </button>
+                <button onClick={() => handleTestConnection('speech')} className="btn-secondary">
+                  测试连接
+                </button>
               </div>
 
               {state.speechProvider === 'azure' && (
@@ -431,6 +940,20 @@ function App() {
                 </>
               )}
 
+              {state.speechProvider === 'tencent' && (
+                <div className="setting-group">
+                  <label htmlFor="speech-api-secret">SecretKey:</label>
+                  <input
+                    id="speech-api-secret"
+                    type="password"
+                    value={state.speechApiSecret}
+                    onChange={(e) => handleConfigChange('speechApiSecret', e.target.value)}
+                    placeholder="腾讯云 SecretKey（API Key 一栏填写 SecretId）"
+                    className="config-input"
+                  />
+                </div>
+              )}
+
               <div className="provider-info">
                 <h4>语音识别: {currentSpeechProvider.name}</h4>
                 <p>{currentSpeechProvider.description}</p>
@@ -452,7 +975,7 @@ function App() {
                   onChange={handleTextProviderChange}
                   className="provider-select"
                 >
-                  {AI_PROVIDERS.map(provider => (
+                  {providers.map(provider => (
                     <option key={provider.id} value={provider.id}>
                       {provider.name} - {provider.description}
                     </option>
@@ -493,9 +1016,69 @@ function App() {
                   onChange={(e) => handleConfigChange('gptModel', e.target.value)}
                   placeholder="GPT 模型"
                   className="config-input"
+                  list="text-models"
                 />
+                <datalist id="text-models">
+                  {textModels.map(name => <option key={name} value={name} />)}
+                </datalist>
               </div>
 
+              <div className="setting-group">
+                <button onClick={() => handleListModels('text')} className="btn-secondary">
+                  获取模型列表
+                </button>
+                <button onClick={() => handleTestConnection('text')} className="btn-secondary">
+                  测试连接
+                </button>
+              </div>
+
+              {state.textProvider === 'ollama' && (
+                <>
+                  <div className="setting-group">
+                    <button onClick={handlePullOllamaModel} className="btn-secondary">
+                      拉取当前模型
+                    </button>
+                  </div>
+
+                  <div className="setting-group">
+                    <label htmlFor="ollama-keep-alive">模型保留时长:</label>
+                    <input
+                      id="ollama-keep-alive"
+                      type="text"
+                      value={state.ollamaKeepAlive}
+                      onChange={(e) => handleConfigChange('ollamaKeepAlive', e.target.value)}
+                      placeholder="如 5m、1h，-1m 表示常驻，留空使用默认"
+                      className="config-input"
+                    />
+                  </div>
+
+                  <div className="setting-group">
+                    <label htmlFor="ollama-num-ctx">上下文长度:</label>
+                    <input
+                      id="ollama-num-ctx"
+                      type="number"
+                      value={state.ollamaNumCtx}
+                      onChange={(e) => handleConfigChange('ollamaNumCtx', e.target.value)}
+                      placeholder="num_ctx，留空使用模型默认"
+                      className="config-input"
+                    />
+                  </div>
+
+                  <div className="setting-group">
+                    <label htmlFor="ollama-temperature">温度:</label>
+                    <input
+                      id="ollama-temperature"
+                      type="number"
+                      step="0.1"
+                      value={state.ollamaTemperature}
+                      onChange={(e) => handleConfigChange('ollamaTemperature', e.target.value)}
+                      placeholder="留空使用模型默认"
+                      className="config-input"
+                    />
+                  </div>
+                </>
+              )}
+
               {state.textProvider === 'azure' && (
                 <>
                   <div className="setting-group">
@@ -524,11 +1107,266 @@ function App() {
                 </>
               )}
 
+              <div className="setting-group">
+                <label htmlFor="prompt-template">提示词模板:</label>
+                <select
+                  id="prompt-template"
+                  value={state.promptTemplate}
+                  onChange={(e) => handleConfigChange('promptTemplate', e.target.value)}
+                  className="config-input"
+                >
+                  <option value="">跟随输出模式</option>
+                  {promptTemplates.map(template => (
+                    <option key={template.id} value={template.id}>
+                      {template.name || template.id}
+                    </option>
+                  ))}
+                </select>
+              </div>
+
+              <div className="setting-group">
+                <label>替换词典（{replacementCount} 条）:</label>
+                <button onClick={handleImportReplacements} className="btn-secondary">
+                  导入
+                </button>
+                <button onClick={handleExportReplacements} className="btn-secondary">
+                  导出
+                </button>
+              </div>
+
+              <div className="setting-group">
+                <label htmlFor="typography-spacing">中英文间空格:</label>
+                <select
+                  id="typography-spacing"
+                  value={state.typographySpacing}
+                  onChange={(e) => handleConfigChange('typographySpacing', e.target.value)}
+                  className="config-input"
+                >
+                  <option value="keep">不处理</option>
+                  <option value="add">添加（用 Rust 写）</option>
+                  <option value="remove">去掉（用Rust写）</option>
+                </select>
+              </div>
+
+              <div className="setting-group">
+                <label htmlFor="typography-punctuation">标点:</label>
+                <select
+                  id="typography-punctuation"
+                  value={state.typographyPunctuation}
+                  onChange={(e) => handleConfigChange('typographyPunctuation', e.target.value)}
+                  className="config-input"
+                >
+                  <option value="keep">不处理</option>
+                  <option value="fullWidth">中文里用全角标点</option>
+                  <option value="halfWidth">全部用半角标点</option>
+                </select>
+              </div>
+
+              <div className="setting-group">
+                <label htmlFor="typography-quotes">引号:</label>
+                <select
+                  id="typography-quotes"
+                  value={state.typographyQuotes}
+                  onChange={(e) => handleConfigChange('typographyQuotes', e.target.value)}
+                  className="config-input"
+                >
+                  <option value="keep">不处理</option>
+                  <option value="curly">“弯引号”</option>
+                  <option value="corner">「直角引号」</option>
+                </select>
+              </div>
+
+              {state.outputMode === 'translate' && (
+                <>
+                  <div className="setting-group">
+                    <label htmlFor="source-language">源语言:</label>
+                    <select
+                      id="source-language"
+                      value={state.sourceLanguage}
+                      onChange={(e) => handleConfigChange('sourceLanguage', e.target.value)}
+                      className="config-input"
+                    >
+                      <option value="">自动识别</option>
+                      {TRANSLATE_LANGUAGES.map(language => (
+                        <option key={language} value={language}>{language}</option>
+                      ))}
+                    </select>
+                  </div>
+                  <div className="setting-group">
+                    <label htmlFor="target-language">目标语言:</label>
+                    <select
+                      id="target-language"
+                      value={state.targetLanguage}
+                      onChange={(e) => handleConfigChange('targetLanguage', e.target.value)}
+                      className="config-input"
+                    >
+                      {TRANSLATE_LANGUAGES.map(language => (
+                        <option key={language} value={language}>{language}</option>
+                      ))}
+                    </select>
+                  </div>
+                  <div className="setting-group">
+                    <label htmlFor="bilingual">翻译结果:</label>
+                    <select
+                      id="bilingual"
+                      value={state.bilingual}
+                      onChange={(e) => handleConfigChange('bilingual', e.target.value)}
+                      className="config-input"
+                    >
+                      <option value="false">仅译文</option>
+                      <option value="true">原文 + 译文</option>
+                    </select>
+                  </div>
+                </>
+              )}
+
+              {supportsSingleCall(state.textProvider) && (
+                <div className="setting-group">
+                  <label htmlFor="pipeline-mode">处理流程:</label>
+                  <select
+                    id="pipeline-mode"
+                    value={state.pipelineMode}
+                    onChange={(e) => handleConfigChange('pipelineMode', e.target.value)}
+                    className="config-input"
+                  >
+                    <option value="separate">先转写再格式化</option>
+                    <option value="single">单次请求（需支持音频输入的模型，如 gpt-4o-audio-preview）</option>
+                  </select>
+                </div>
+              )}
+
               <div className="provider-info">
                 <h4>文本处理: {currentTextProvider.name}</h4>
                 <p>{currentTextProvider.description}</p>
               </div>
             </div>
+
+            {/* Azure Entra ID 鉴权 */}
+            {(state.speechProvider === 'azure' || state.textProvider === 'azure') && (
+              <div className="provider-section">
+                <h3>🔐 Azure 鉴权</h3>
+
+                <div className="setting-group">
+                  <label htmlFor="azure-auth-mode">鉴权方式:</label>
+                  <select
+                    id="azure-auth-mode"
+                    value={state.azureAuthMode}
+                    onChange={(e) => handleConfigChange('azureAuthMode', e.target.value)}
+                    className="config-input"
+                  >
+                    <option value="apiKey">API Key</option>
+                    <option value="entraId">Entra ID（客户端凭据）</option>
+                  </select>
+                </div>
+
+                {state.azureAuthMode === 'entraId' && (
+                  <>
+                    <div className="setting-group">
+                      <label htmlFor="azure-tenant-id">租户 ID:</label>
+                      <input
+                        id="azure-tenant-id"
+                        type="text"
+                        value={state.azureTenantId}
+                        onChange={(e) => handleConfigChange('azureTenantId', e.target.value)}
+                        placeholder="Directory (tenant) ID"
+                        className="config-input"
+                      />
+                    </div>
+
+                    <div className="setting-group">
+                      <label htmlFor="azure-client-id">客户端 ID:</label>
+                      <input
+                        id="azure-client-id"
+                        type="text"
+                        value={state.azureClientId}
+                        onChange={(e) => handleConfigChange('azureClientId', e.target.value)}
+                        placeholder="Application (client) ID"
+                        className="config-input"
+                      />
+                    </div>
+
+                    <div className="setting-group">
+                      <label htmlFor="azure-client-secret">客户端密码:</label>
+                      <input
+                        id="azure-client-secret"
+                        type="password"
+                        value={state.azureClientSecret}
+                        onChange={(e) => handleConfigChange('azureClientSecret', e.target.value)}
+                        placeholder="Client secret"
+                        className="config-input"
+                      />
+                    </div>
+
+                    <div className="setting-group">
+                      <label htmlFor="azure-token-endpoint">令牌地址:</label>
+                      <input
+                        id="azure-token-endpoint"
+                        type="text"
+                        value={state.azureTokenEndpoint}
+                        onChange={(e) => handleConfigChange('azureTokenEndpoint', e.target.value)}
+                        placeholder="留空使用 login.microsoftonline.com"
+                        className="config-input"
+                      />
+                    </div>
+                  </>
+                )}
+              </div>
+            )}
+
+            {/* 网络配置 */}
+            <div className="provider-section">
+              <h3>🌐 网络配置</h3>
+
+              <div className="setting-group">
+                <label htmlFor="network-proxy">代理地址:</label>
+                <input
+                  id="network-proxy"
+                  type="text"
+                  value={state.networkProxy}
+                  onChange={(e) => handleConfigChange('networkProxy', e.target.value)}
+                  placeholder="http://127.0.0.1:7890 或 socks5://127.0.0.1:1080，留空直连"
+                  className="config-input"
+                />
+              </div>
+
+              <div className="setting-group">
+                <label htmlFor="ca-certificates">自定义根证书:</label>
+                <input
+                  id="ca-certificates"
+                  type="text"
+                  value={state.caCertificates}
+                  onChange={(e) => handleConfigChange('caCertificates', e.target.value)}
+                  placeholder="证书文件路径，多个用逗号分隔"
+                  className="config-input"
+                />
+              </div>
+
+              <div className="setting-group">
+                <label htmlFor="retry-max-retries">失败重试次数:</label>
+                <input
+                  id="retry-max-retries"
+                  type="number"
+                  min="0"
+                  value={state.retryMaxRetries}
+                  onChange={(e) => handleConfigChange('retryMaxRetries', e.target.value)}
+                  placeholder="限流、超时和服务端错误时重试，留空为 3 次"
+                  className="config-input"
+                />
+              </div>
+
+              <div className="setting-group">
+                <label htmlFor="retry-deadline">重试总时长（秒）:</label>
+                <input
+                  id="retry-deadline"
+                  type="number"
+                  min="0"
+                  value={state.retryDeadline}
+                  onChange={(e) => handleConfigChange('retryDeadline', e.target.value)}
+                  placeholder="超过后不再重试，留空为 60 秒"
+                  className="config-input"
+                />
+              </div>
+            </div>
           </div>
         )}
 
@@ -544,7 +1382,7 @@ function App() {
                   onChange={handleSpeechProviderChange}
                   className="config-input"
                 >
-                  {AI_PROVIDERS.map(provider => (
+                  {providers.map(provider => (
                     <option key={provider.id} value={provider.id}>
                       {provider.name}
                     </option>
@@ -595,7 +1433,7 @@ function App() {
                   onChange={handleTextProviderChange}
                   className="config-input"
                 >
-                  {AI_PROVIDERS.map(provider => (
+                  {providers.map(provider => (
                     <option key={provider.id} value={provider.id}>
                       {provider.name}
                     </option>